@@ -1,19 +1,40 @@
-use std::{
-    path::Path,
-    process::Output
-};
+use std::{collections::HashMap, fs::OpenOptions, io::Write, process::Output, sync::{Arc, Mutex}, time::Duration};
 
-use system::{Logger, EnvConfig};
+use chrono::{NaiveDateTime, Utc};
+use system::{Logger, EnvConfig, Path};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::Semaphore
+};
 
 use crate::{
-    cmd_engine::CmdEngine, 
+    cmd_engine::{CmdEngine, ExecSpec}, 
     db::Database, 
     errors::LiaCoreError, 
-    models::command::{Command, NewCommand, UpdateCommand}
+    risk::{RiskAnalyzer, RiskFinding},
+    secrets::{self, SecretKey, KDF_ITERATIONS, PASSPHRASE_ENV},
+    shell,
+    models::{
+        command::{Command, NewCommand, UpdateCommand},
+        job::{Job, JobStatus},
+        retry::{self, Attempt},
+        schedule::{next_run_after, CatchUp, NewSchedule, Schedule},
+        run::{parse_env_var, CancelHandle, ExecEvent, RenderedCommand, RunHandle, RunOptions},
+        secret::{Secret, SecretStore}
+    }
 };
 
 pub struct LiaCore {
     pub db: Database,
+    /// Set once `unlock_secrets` succeeded.
+    secrets: Mutex<Option<UnlockedSecrets>>,
+}
+
+struct UnlockedSecrets {
+    key: SecretKey,
+    /// Handed to the supervisors of jobs that use secrets, which unlock the store again.
+    passphrase: String,
+    values: HashMap<String, String>,
 }
 
 impl LiaCore {
@@ -32,7 +53,7 @@ impl LiaCore {
         match db {
             Ok(pool) => {
                 Logger::info("Database connection established.", true);
-                Ok(Self { db: pool })
+                Ok(Self { db: pool, secrets: Mutex::new(None) })
             },
             Err(e) => {
                 Logger::error(&format!("Failed to connect to database: {}", e), true);
@@ -43,6 +64,7 @@ impl LiaCore {
 
     pub async fn add_command(&self, new_cmd: NewCommand) -> Result<(), LiaCoreError> {
         Logger::info(&format!("Adding command: {}", new_cmd.name), true);
+        Self::validate_env_vars(new_cmd.env_vars.as_deref())?;
         let comm = self.db.add_command(new_cmd).await;
         match comm {
             Ok(_) => Ok(Logger::info("Command added successfully.", true)),
@@ -53,8 +75,15 @@ impl LiaCore {
         }
     }
 
-    pub async fn update_command(&self, cmd: UpdateCommand) -> Result<(), LiaCoreError> {
+    pub async fn update_command(&self, mut cmd: UpdateCommand) -> Result<(), LiaCoreError> {
         Logger::info(&format!("Updating command: {}", cmd.name), true);
+        Self::validate_env_vars(cmd.new_env_vars.as_deref())?;
+        // Keep the token list of argv-mode commands in sync with their new text.
+        if let (Some(text), None) = (&cmd.new_command_text, &cmd.new_argv) {
+            if self.db.get_command_by_name(&cmd.name).await?.argv.is_some() {
+                cmd.new_argv = Some(shell::split(text)?);
+            }
+        }
         let comm = self.db.update_command(cmd).await;
         match comm {
             Ok(_) => Ok(Logger::info("Command updated successfully.", true)),
@@ -95,21 +124,548 @@ impl LiaCore {
         }
     }
 
+    pub async fn add_aliases(&self, cmd_name: &str, aliases: &[String]) -> Result<(), LiaCoreError> {
+        Logger::info(format!("Adding aliases {:?} to command: {}", aliases, cmd_name), true);
+        let res = self.db.add_aliases(cmd_name, aliases).await;
+        match res {
+            Ok(_) => {
+                Logger::info("Aliases added successfully.", true);
+                Ok(())
+            },
+            Err(e) => {
+                Logger::error(format!("Failed to add aliases: {}", e), true);
+                Err(e)
+            },
+        }
+    }
+
+    pub async fn remove_aliases(&self, aliases: &[String]) -> Result<(), LiaCoreError> {
+        Logger::info(format!("Removing aliases: {:?}", aliases), true);
+        let res = self.db.remove_aliases(aliases).await;
+        match res {
+            Ok(_) => {
+                Logger::info("Aliases removed successfully.", true);
+                Ok(())
+            },
+            Err(e) => {
+                Logger::error(format!("Failed to remove aliases: {}", e), true);
+                Err(e)
+            },
+        }
+    }
+
     pub async fn search_commands(&self, query: &str, tags: Option<Vec<String>>, limit: i64, offset: i64) -> Result<Vec<Command>, LiaCoreError> {
         self.db.search_commands(query, tags, limit, offset).await
     }
 
-    pub async fn run_command(&self, cmd: Command, path: &Path) -> Result<Output, LiaCoreError> {
-        CmdEngine::execute_command(&cmd.command_text, path)
+    /// Resolves what `run_command` would spawn for `cmd` and `options`, without running anything.
+    pub fn render_command(&self, cmd: &Command, options: &RunOptions) -> Result<RenderedCommand, LiaCoreError> {
+        Ok(ExecSpec::new(cmd, options)?.render())
+    }
+
+    /// Risk rules from `configs.json` that match `command_text`.
+    pub fn analyze_risk(&self, command_text: &str) -> Result<Vec<RiskFinding>, LiaCoreError> {
+        Ok(RiskAnalyzer::from_configs()?.analyze(command_text))
+    }
+
+    /// Risk rules that match what `cmd` would run with `options`, run-time arguments included.
+    pub fn assess_run_risk(&self, cmd: &Command, options: &RunOptions) -> Result<Vec<RiskFinding>, LiaCoreError> {
+        let rendered = self.render_command(cmd, options)?;
+        let text = rendered.script.unwrap_or_else(|| shell::join(&rendered.argv));
+        self.analyze_risk(&text)
+    }
+
+    /// Whether running `cmd` with `options` needs the secret store unlocked.
+    pub fn uses_secrets(&self, cmd: &Command, options: &RunOptions) -> Result<bool, LiaCoreError> {
+        Ok(!ExecSpec::new(cmd, options)?.secret_names().is_empty())
+    }
+
+    /// `ExecSpec::new` with the values of the secrets the run references.
+    fn exec_spec(&self, cmd: &Command, options: &RunOptions) -> Result<ExecSpec, LiaCoreError> {
+        let mut spec = ExecSpec::new(cmd, options)?;
+        let names = spec.secret_names();
+        if names.is_empty() {
+            return Ok(spec);
+        }
+
+        let unlocked = self.secrets.lock().unwrap();
+        let unlocked = unlocked.as_ref().ok_or_else(|| {
+            LiaCoreError::SecretError(format!("{} uses secrets, but the secret store is locked", cmd.name))
+        })?;
+        for name in names {
+            let value = unlocked.values.get(&name).ok_or(LiaCoreError::SecretNotFoundError(name.clone()))?;
+            spec.secrets.insert(name, value.clone());
+        }
+        Ok(spec)
+    }
+
+    /// Runs `cmd` once and returns its output; only streamed runs are retried. `cancel` stops it early.
+    pub async fn run_command(&self, cmd: Command, options: RunOptions, cancel: &CancelHandle) -> Result<Output, LiaCoreError> {
+        let spec = self.exec_spec(&cmd, &options)?;
+        CmdEngine::execute_command(&spec, cancel).await
     }
 
     pub async fn run_command_stream(
         &self,
         cmd: Command,
-        path: &Path,
-        output_tx: tokio::sync::mpsc::UnboundedSender<String>
+        options: RunOptions,
+        output_tx: tokio::sync::mpsc::UnboundedSender<ExecEvent>
     ) -> Result<(), LiaCoreError> {
-        CmdEngine::execute_command_stream(&cmd.command_text, path, output_tx)
+        self.start_command_stream(cmd, options, output_tx)?.wait().await
+    }
+
+    /// Starts streaming a command in the background and returns a handle to cancel or wait for it.
+    pub fn start_command_stream(
+        &self,
+        cmd: Command,
+        options: RunOptions,
+        output_tx: tokio::sync::mpsc::UnboundedSender<ExecEvent>
+    ) -> Result<RunHandle, LiaCoreError> {
+        let spec = self.exec_spec(&cmd, &options)?;
+        let cancel = CancelHandle::new();
+        let task_cancel = cancel.clone();
+        let task = tokio::spawn(async move { CmdEngine::execute_with_retries(&spec, output_tx, &task_cancel).await });
+        Ok(RunHandle::new(cancel, task))
+    }
+
+    /// Starts `cmd` as a background job that outlives the caller. `supervisor` is a program and arguments
+    /// that get re-executed detached with the job id appended; that process must call `supervise_job`.
+    pub async fn start_job(&self, cmd: Command, options: RunOptions, supervisor: &[String]) -> Result<Job, LiaCoreError> {
+        self.launch_job(cmd, options, supervisor, None).await
+    }
+
+    /// `schedule` is the schedule id and the time it wanted the run to start, for runs started by the daemon.
+    async fn launch_job(
+        &self,
+        cmd: Command,
+        mut options: RunOptions,
+        supervisor: &[String],
+        schedule: Option<(i32, NaiveDateTime)>,
+    ) -> Result<Job, LiaCoreError> {
+        Logger::info(format!("Starting job for command: {}", cmd.name), true);
+        if options.tty || cmd.tty {
+            return Err(LiaCoreError::InvalidInputError(String::from(
+                "Commands that need a terminal can't run as background jobs",
+            )));
+        }
+        // Jobs have no terminal or caller to read from.
+        options.stdin = None;
+        // Surface bad options and a locked secret store now rather than in the job's log.
+        ExecSpec::new(&cmd, &options)?;
+        self.unlock_secrets_for(&cmd, &options).await?;
+        let passphrase = if self.uses_secrets(&cmd, &options)? {
+            self.secrets.lock().unwrap().as_ref().map(|unlocked| unlocked.passphrase.clone())
+        } else {
+            None
+        };
+
+        let run_options = serde_json::to_string(&options)
+            .map_err(|e| LiaCoreError::InvalidInputError(format!("Could not save run options: {}", e)))?;
+        let log_dir = Path::get_job_logs_path();
+        std::fs::create_dir_all(&log_dir)?;
+        let (schedule_id, scheduled_for) = schedule.unzip();
+        let job = self.db
+            .add_job(&cmd.name, &run_options, &log_dir.to_string_lossy(), schedule_id, scheduled_for)
+            .await?;
+
+        let (program, args) = supervisor.split_first().ok_or_else(|| {
+            LiaCoreError::InvalidInputError(String::from("No supervisor program given"))
+        })?;
+        let mut args = args.to_vec();
+        args.push(job.id.to_string());
+        match CmdEngine::spawn_detached(program, &args, passphrase.as_deref()) {
+            Ok(pid) => {
+                self.db.set_job_pid(job.id, pid as i32).await?;
+                Logger::info(format!("Job {} started.", job.id), true);
+                self.db.get_job(job.id).await
+            },
+            Err(e) => {
+                Logger::error(format!("Failed to start job {}: {}", job.id, e), true);
+                self.db.finish_job(job.id, JobStatus::Failed, None, None).await?;
+                Err(e)
+            },
+        }
+    }
+
+    /// Runs a job created by `start_job` to completion, writing its output to the job's log and recording
+    /// how it ended. Meant to be called by the detached supervisor process; `SIGTERM` cancels the job.
+    pub async fn supervise_job(&self, job_id: i32) -> Result<(), LiaCoreError> {
+        let job = self.db.get_job(job_id).await?;
+        let mut log = OpenOptions::new().create(true).append(true).open(&job.log_path)?;
+        // Installed first so a kill right after the start isn't missed.
+        let mut terminate = signal(SignalKind::terminate())?;
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let run = match serde_json::from_str::<RunOptions>(&job.run_options) {
+            Ok(options) => match self.db.get_command_by_name(&job.command_name).await {
+                Ok(cmd) => match self.unlock_secrets_for(&cmd, &options).await {
+                    Ok(_) => self.start_command_stream(cmd, options, tx),
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(LiaCoreError::InvalidInputError(format!("Invalid run options: {}", e))),
+        };
+        let run = match run {
+            Ok(run) => run,
+            Err(e) => {
+                writeln!(log, "lia: {}", e)?;
+                return self.db.finish_job(job_id, JobStatus::Failed, None, None).await;
+            },
+        };
+
+        let cancel = run.cancel_handle();
+        tokio::spawn(async move {
+            if terminate.recv().await.is_some() {
+                cancel.cancel();
+            }
+        });
+
+        let (mut exit_code, mut exit_signal) = (None, None);
+        while let Some(event) = rx.recv().await {
+            match event {
+                ExecEvent::Stdout(line) | ExecEvent::Stderr(line) => writeln!(log, "{}", line)?,
+                ExecEvent::Exited { code, signal, .. } => {
+                    exit_code = code;
+                    exit_signal = signal;
+                },
+                ExecEvent::Retrying { attempt, max_attempts, delay } => {
+                    writeln!(log, "lia: attempt {} of {} failed ({}), retrying in {:?}", attempt.number, max_attempts, attempt, delay)?;
+                    self.db.add_job_attempt(job_id, &attempt).await?;
+                },
+                ExecEvent::Attempts(attempts) => {
+                    if let Some(last) = attempts.last() {
+                        self.db.add_job_attempt(job_id, last).await?;
+                    }
+                    if attempts.len() > 1 {
+                        for line in retry::summarize(&attempts) {
+                            writeln!(log, "lia: {}", line)?;
+                        }
+                    }
+                },
+            }
+        }
+
+        let status = match run.wait().await {
+            Ok(_) => JobStatus::Succeeded,
+            Err(LiaCoreError::CommandCancelledError) => JobStatus::Killed,
+            Err(LiaCoreError::CommandTimeoutError(_)) => JobStatus::TimedOut,
+            Err(e) => {
+                writeln!(log, "lia: {}", e)?;
+                JobStatus::Failed
+            },
+        };
+        self.db.finish_job(job_id, status, exit_code, exit_signal).await
+    }
+
+    pub async fn get_jobs(&self, limit: i64) -> Result<Vec<Job>, LiaCoreError> {
+        let mut jobs = self.db.get_jobs(limit).await?;
+        for job in jobs.iter_mut() {
+            self.check_job_alive(job).await?;
+        }
+        Ok(jobs)
+    }
+
+    pub async fn get_job(&self, job_id: i32) -> Result<Job, LiaCoreError> {
+        let mut job = self.db.get_job(job_id).await?;
+        self.check_job_alive(&mut job).await?;
+        Ok(job)
+    }
+
+    /// How each attempt of a job with a retry policy ended, first to last.
+    pub async fn get_job_attempts(&self, job_id: i32) -> Result<Vec<Attempt>, LiaCoreError> {
+        self.db.get_job(job_id).await?;
+        self.db.get_job_attempts(job_id).await
+    }
+
+    /// Asks a running job to stop. Its command gets `SIGTERM`, then `SIGKILL` after the grace period.
+    pub async fn kill_job(&self, job_id: i32) -> Result<(), LiaCoreError> {
+        Logger::info(format!("Killing job: {}", job_id), true);
+        let job = self.get_job(job_id).await?;
+        match (job.is_finished(), job.pid) {
+            (false, Some(pid)) => CmdEngine::signal_process(pid, libc::SIGTERM),
+            _ => Err(LiaCoreError::InvalidInputError(format!("Job {} is not running", job_id))),
+        }
+    }
+
+    /// Waits until the job finishes and returns its final state.
+    pub async fn wait_job(&self, job_id: i32) -> Result<Job, LiaCoreError> {
+        loop {
+            let job = self.get_job(job_id).await?;
+            if job.is_finished() {
+                return Ok(job);
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    pub async fn add_schedule(&self, new_schedule: NewSchedule) -> Result<Schedule, LiaCoreError> {
+        Logger::info(format!("Adding schedule for command: {}", new_schedule.command_name), true);
+        Self::check_schedulable(&self.db.get_command_by_name(&new_schedule.command_name).await?)?;
+        let next_run_at = next_run_after(
+            new_schedule.cron_expr.as_deref(),
+            new_schedule.interval_secs,
+            Utc::now().naive_utc(),
+        )?;
+        let res = self.db.add_schedule(new_schedule, next_run_at).await;
+        match res {
+            Ok(schedule) => {
+                Logger::info(format!("Schedule {} added successfully.", schedule.id), true);
+                Ok(schedule)
+            },
+            Err(e) => {
+                Logger::error(format!("Failed to add schedule: {}", e), true);
+                Err(e)
+            },
+        }
+    }
+
+    /// Scheduled runs are detached jobs with no terminal, so sudo couldn't ask for a password there.
+    fn check_schedulable(cmd: &Command) -> Result<(), LiaCoreError> {
+        if cmd.requires_root {
+            return Err(LiaCoreError::InvalidInputError(format!(
+                "{} requires root, and scheduled runs have no terminal for sudo to ask for a password on",
+                cmd.name
+            )));
+        }
+        Ok(())
+    }
+
+    pub async fn get_schedules(&self) -> Result<Vec<Schedule>, LiaCoreError> {
+        self.db.get_schedules().await
+    }
+
+    pub async fn remove_schedule(&self, schedule_id: i32) -> Result<(), LiaCoreError> {
+        Logger::info(format!("Removing schedule: {}", schedule_id), true);
+        self.db.remove_schedule(schedule_id).await
+    }
+
+    /// Runs started by a schedule, newest first.
+    pub async fn get_schedule_history(&self, schedule_id: i32, limit: i64) -> Result<Vec<Job>, LiaCoreError> {
+        self.db.get_schedule(schedule_id).await?;
+        let mut jobs = self.db.get_schedule_jobs(schedule_id, limit).await?;
+        for job in jobs.iter_mut() {
+            self.check_job_alive(job).await?;
+        }
+        Ok(jobs)
+    }
+
+    /// Starts due schedules as jobs until the process is stopped; `supervisor` is the same as for `start_job`.
+    /// Only one daemon runs per database. Jobs it started keep running if it stops.
+    pub async fn run_daemon(&self, supervisor: &[String]) -> Result<(), LiaCoreError> {
+        let _lock = self.db.lock_daemon().await?;
+        Logger::info("Daemon started.", true);
+
+        let now = Utc::now().naive_utc();
+        for schedule in self.db.get_due_schedules(now).await? {
+            if schedule.catch_up == CatchUp::Skip {
+                Logger::info(format!("Skipping missed runs of schedule {}.", schedule.id), true);
+                self.db.set_schedule_runs(schedule.id, schedule.next_run_after(now)?, schedule.last_run_at).await?;
+            }
+        }
+
+        loop {
+            let now = Utc::now().naive_utc();
+            for schedule in self.db.get_due_schedules(now).await? {
+                if let Err(e) = self.run_schedule(&schedule, supervisor, now).await {
+                    Logger::error(format!("Failed to run schedule {}: {}", schedule.id, e), true);
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    async fn run_schedule(&self, schedule: &Schedule, supervisor: &[String], now: NaiveDateTime) -> Result<(), LiaCoreError> {
+        let mut previous_runs = self.db.get_schedule_jobs(schedule.id, 1).await?;
+        for job in previous_runs.iter_mut() {
+            self.check_job_alive(job).await?;
+        }
+
+        // Never overlap runs of one schedule: `all` waits for the previous run, the others skip this one.
+        if previous_runs.iter().any(|job| !job.is_finished()) {
+            if schedule.catch_up != CatchUp::All {
+                Logger::warn(format!("Skipping run of schedule {}: the previous run is still going.", schedule.id), true);
+                self.db.set_schedule_runs(schedule.id, schedule.next_run_after(now)?, schedule.last_run_at).await?;
+            }
+            return Ok(());
+        }
+
+        let next_run_at = match schedule.catch_up {
+            CatchUp::All => schedule.next_run_after(schedule.next_run_at)?,
+            CatchUp::Skip | CatchUp::Once => schedule.next_run_after(now)?,
+        };
+        // Moved on before starting, so a run that fails to start isn't retried every second.
+        self.db.set_schedule_runs(schedule.id, next_run_at, Some(now)).await?;
+
+        let cmd = self.db.get_command_by_name(&schedule.command_name).await?;
+        // The command may have changed since it was scheduled.
+        Self::check_schedulable(&cmd)?;
+        self.launch_job(cmd, RunOptions::default(), supervisor, Some((schedule.id, schedule.next_run_at))).await?;
+        Ok(())
+    }
+
+    /// Marks a running job as lost when its supervisor is gone, e.g. after a reboot.
+    async fn check_job_alive(&self, job: &mut Job) -> Result<(), LiaCoreError> {
+        if let (JobStatus::Running, Some(pid)) = (job.status, job.pid) {
+            if !CmdEngine::is_process_alive(pid) {
+                self.db.finish_job(job.id, JobStatus::Lost, None, None).await?;
+                *job = self.db.get_job(job.id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Streams several commands at once, at most `limit` at a time. Events are tagged with the run's index in
+    /// `runs`; results come back in the same order. Cancelling `cancel` stops every run, started or not.
+    pub async fn run_commands_parallel(
+        &self,
+        runs: Vec<(Command, RunOptions)>,
+        limit: usize,
+        output_tx: tokio::sync::mpsc::UnboundedSender<(usize, ExecEvent)>,
+        cancel: &CancelHandle,
+    ) -> Vec<Result<(), LiaCoreError>> {
+        let semaphore = Arc::new(Semaphore::new(limit.max(1)));
+
+        let tasks: Vec<_> = runs
+            .iter()
+            .enumerate()
+            .map(|(index, (cmd, options))| {
+                let spec = self.exec_spec(cmd, options);
+                let semaphore = semaphore.clone();
+                let output_tx = output_tx.clone();
+                let cancel = cancel.clone();
+                tokio::spawn(async move {
+                    let spec = spec?;
+                    if spec.tty {
+                        return Err(LiaCoreError::InvalidInputError(String::from(
+                            "Commands that need a terminal can't run in parallel",
+                        )));
+                    }
+                    let _permit = semaphore.acquire_owned().await;
+                    if cancel.is_cancelled() {
+                        return Err(LiaCoreError::CommandCancelledError);
+                    }
+
+                    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+                    let forward = tokio::spawn(async move {
+                        while let Some(event) = rx.recv().await {
+                            let _ = output_tx.send((index, event));
+                        }
+                    });
+                    let result = CmdEngine::execute_with_retries(&spec, tx, &cancel).await;
+                    let _ = forward.await;
+                    result
+                })
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            results.push(task.await.unwrap_or_else(|e| {
+                Err(LiaCoreError::CommandExecutionError {
+                    message: format!("Run task failed: {}", e),
+                    code: None,
+                    signal: None,
+                })
+            }));
+        }
+        results
+    }
+
+    pub async fn has_secret_store(&self) -> Result<bool, LiaCoreError> {
+        Ok(self.db.get_secret_store().await?.is_some())
+    }
+
+    pub fn secrets_unlocked(&self) -> bool {
+        self.secrets.lock().unwrap().is_some()
+    }
+
+    /// Derives the store's key from `passphrase` and decrypts every secret. The first call creates the store,
+    /// and its passphrase is the one to use from then on.
+    pub async fn unlock_secrets(&self, passphrase: &str) -> Result<(), LiaCoreError> {
+        let key = match self.db.get_secret_store().await? {
+            Some(store) => {
+                let iterations = u32::try_from(store.iterations)
+                    .map_err(|_| LiaCoreError::SecretError(String::from("Invalid key derivation settings")))?;
+                let key = SecretKey::derive(passphrase, &store.salt, iterations);
+                if !key.matches(&store.verifier) {
+                    return Err(LiaCoreError::SecretError(String::from("Wrong passphrase")));
+                }
+                key
+            }
+            None => {
+                Logger::info("Creating the secret store.", true);
+                let salt = secrets::random_salt();
+                let key = SecretKey::derive(passphrase, &salt, KDF_ITERATIONS);
+                let store = SecretStore { salt, iterations: KDF_ITERATIONS as i32, verifier: key.verifier() };
+                self.db.create_secret_store(&store).await?;
+                key
+            }
+        };
+
+        let mut values = HashMap::new();
+        for (name, secret) in self.db.get_encrypted_secrets().await? {
+            let value = key.decrypt(&name, &secret)?;
+            values.insert(name, value);
+        }
+        *self.secrets.lock().unwrap() = Some(UnlockedSecrets { key, passphrase: passphrase.to_string(), values });
+        Ok(())
+    }
+
+    /// Unlocks the store with the passphrase in `LIA_SECRET_PASSPHRASE` if the run needs it, for runs
+    /// without a terminal such as jobs.
+    async fn unlock_secrets_for(&self, cmd: &Command, options: &RunOptions) -> Result<(), LiaCoreError> {
+        if self.secrets_unlocked() || !self.uses_secrets(cmd, options)? {
+            return Ok(());
+        }
+        match std::env::var(PASSPHRASE_ENV) {
+            Ok(passphrase) => self.unlock_secrets(&passphrase).await,
+            Err(_) => Err(LiaCoreError::SecretError(format!(
+                "{} uses secrets, but {} isn't set to unlock the secret store",
+                cmd.name, PASSPHRASE_ENV
+            ))),
+        }
+    }
+
+    /// Stores a secret, replacing its value if it exists. The store must be unlocked.
+    pub async fn set_secret(&self, name: &str, value: &str) -> Result<(), LiaCoreError> {
+        secrets::validate_name(name)?;
+        let encrypted = {
+            let unlocked = self.secrets.lock().unwrap();
+            let unlocked = unlocked.as_ref().ok_or_else(|| LiaCoreError::SecretError(String::from("The secret store is locked")))?;
+            unlocked.key.encrypt(name, value)
+        };
+        self.db.set_secret(name, &encrypted).await?;
+        Logger::info(format!("Secret {} saved.", name), true);
+
+        if let Some(unlocked) = self.secrets.lock().unwrap().as_mut() {
+            unlocked.values.insert(name.to_string(), value.to_string());
+        }
+        Ok(())
+    }
+
+    /// A secret's value. The store must be unlocked.
+    pub fn get_secret(&self, name: &str) -> Result<String, LiaCoreError> {
+        let unlocked = self.secrets.lock().unwrap();
+        let unlocked = unlocked.as_ref().ok_or_else(|| LiaCoreError::SecretError(String::from("The secret store is locked")))?;
+        unlocked.values.get(name).cloned().ok_or_else(|| LiaCoreError::SecretNotFoundError(name.to_string()))
+    }
+
+    pub async fn remove_secret(&self, name: &str) -> Result<(), LiaCoreError> {
+        self.db.remove_secret(name).await?;
+        Logger::info(format!("Secret {} removed.", name), true);
+        Ok(())
+    }
+
+    pub async fn get_secrets(&self) -> Result<Vec<Secret>, LiaCoreError> {
+        self.db.get_secrets().await
+    }
+
+    fn validate_env_vars(env_vars: Option<&[String]>) -> Result<(), LiaCoreError> {
+        for pair in env_vars.unwrap_or_default() {
+            parse_env_var(pair)?;
+        }
+        Ok(())
     }
 
     pub fn is_sudo_user() -> bool {
@@ -1,92 +1,865 @@
-use crate::errors::LiaCoreError;
+use crate::{
+    errors::LiaCoreError,
+    sandbox::SandboxSetup,
+    secrets::{self, SecretMask, PASSPHRASE_ENV},
+    pty::{self, Pty, RawModeGuard},
+    shell,
+    models::{
+        command::Command as StoredCommand,
+        interpreter::Interpreter,
+        limits::{IoPriority, ResourceLimits},
+        retry::{Attempt, RetryPolicy},
+        run::{parse_env_var, CancelHandle, ExecEvent, RenderedCommand, RunOptions, Sandbox, StdinSource}
+    },
+};
 use std::{
-    io::{BufRead, BufReader}, 
-    path::Path, 
-    process::{Command, Output, Stdio}
+    collections::HashMap,
+    ffi::CStr,
+    os::{fd::AsRawFd, unix::process::{CommandExt, ExitStatusExt}},
+    path::PathBuf, 
+    process::{ExitStatus, Output, Stdio},
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
+    time::{Duration, Instant}
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    process::{Child, Command},
+    signal::unix::{signal, SignalKind},
+    sync::mpsc::UnboundedSender
 };
+use regex::Regex;
 
-#[cfg(target_os = "linux")]
-use libc;
+use system::SysConfigs;
 
-pub struct CmdEngine;
+/// A stored command resolved against its run options, ready to be spawned.
+pub struct ExecSpec {
+    /// Name of the stored command; shells see it as `$0`.
+    pub name: String,
+    pub command_text: String,
+    pub interpreter: Interpreter,
+    /// Program and arguments for direct execution; `None` means the interpreter runs `command_text`.
+    pub argv: Option<Vec<String>>,
+    pub cwd: PathBuf,
+    pub env: Vec<(String, String)>,
+    pub timeout: Option<Duration>,
+    /// Run on a pseudo-terminal attached to LiA's terminal instead of capturing output.
+    pub tty: bool,
+    pub stdin: Option<StdinSource>,
+    /// Non-zero exit codes that still count as success.
+    pub ok_exit_codes: Vec<i32>,
+    pub args: Vec<String>,
+    /// Run through `sudo`, for commands that require root when LiA isn't root.
+    pub sudo: bool,
+    /// Run as this user instead of root, for commands that don't require root when LiA runs under sudo.
+    pub run_as: Option<RunAsUser>,
+    pub limits: ResourceLimits,
+    /// Resolved sandbox settings, with what the child needs to enter it.
+    pub sandbox: Option<(Sandbox, SandboxSetup)>,
+    /// Values of the secrets the run references, filled in just before it starts.
+    pub secrets: HashMap<String, String>,
+    pub retry: RetryPolicy,
+    /// `retry.on_output`, compiled.
+    pub retry_output: Option<Regex>,
+}
 
-impl CmdEngine {
-    pub fn execute_command(command_text: &str, path: &Path) -> Result<Output, LiaCoreError> {
-        if !path.exists() || !path.is_dir() {
-            return Err(LiaCoreError::InvalidInputError(format!(
-                "Path does not exist or is not a directory: {}",
-                path.display()
+/// An unprivileged user commands switch to before they start.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunAsUser {
+    pub uid: u32,
+    pub gid: u32,
+    /// Supplementary groups, looked up beforehand since the child can't safely read `/etc/group`.
+    pub groups: Vec<libc::gid_t>,
+    pub name: String,
+    pub home: Option<PathBuf>,
+}
+
+impl ExecSpec {
+    pub fn new(cmd: &StoredCommand, options: &RunOptions) -> Result<Self, LiaCoreError> {
+        let cwd = match (&options.cwd, &cmd.working_dir) {
+            (Some(cwd), _) => CmdEngine::expand_path(&cwd.to_string_lossy())?,
+            (None, Some(dir)) => CmdEngine::expand_path(dir)?,
+            (None, None) => std::env::current_dir().map_err(LiaCoreError::IoError)?,
+        };
+
+        // Under sudo, only commands that require root keep it.
+        let run_as = if !cmd.requires_root && SysConfigs::get_execution().drop_privileges {
+            CmdEngine::invoking_user()
+        } else {
+            None
+        };
+
+        // The invoking user's identity comes first, then stored variables, then run-time ones, so later ones win.
+        let mut env = Vec::new();
+        if let Some(user) = &run_as {
+            env.push((String::from("USER"), user.name.clone()));
+            env.push((String::from("LOGNAME"), user.name.clone()));
+            if let Some(home) = &user.home {
+                env.push((String::from("HOME"), home.to_string_lossy().into_owned()));
+            }
+        }
+        for pair in cmd.env_vars.iter().flatten() {
+            env.push(parse_env_var(pair)?);
+        }
+        env.extend(options.env.iter().cloned());
+
+        let interpreter = match &cmd.interpreter {
+            Some(name) => Interpreter::parse(name),
+            None => Interpreter::parse(&SysConfigs::get_execution().default_interpreter),
+        };
+
+        let timeout = options.timeout.or_else(|| {
+            cmd.timeout_ms
+                .and_then(|ms| u64::try_from(ms).ok())
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis)
+        });
+
+        let sudo = cmd.requires_root && !CmdEngine::is_sudo_user();
+        if sudo && !SysConfigs::get_execution().elevate_with_sudo {
+            return Err(LiaCoreError::RootRequiredError(cmd.name.clone()));
+        }
+
+        let limits = cmd.resource_limits()?.merge(&options.limits);
+        let favoured = limits.nice.is_some_and(|nice| nice < 0) || matches!(limits.io_priority, Some(IoPriority::Realtime(_)));
+        if favoured && !CmdEngine::is_sudo_user() {
+            return Err(LiaCoreError::InvalidInputError(String::from(
+                "Negative nice values and realtime I/O priority need LiA to run as root",
             )));
         }
 
-        let output = Command::new("sh")
-            .arg("-c")
-            .arg(command_text)
-            .current_dir(path)
-            .output()
-            .map_err(LiaCoreError::IoError)?;
+        let tty = options.tty || cmd.tty;
+        let sandbox = match &options.sandbox {
+            // On a pty the command gets its own session, out of reach of the signals that stop the sandbox's.
+            Some(_) if tty => {
+                return Err(LiaCoreError::InvalidInputError(String::from(
+                    "Sandboxed commands can't run on a pseudo-terminal",
+                )));
+            }
+            Some(_) if sudo => {
+                return Err(LiaCoreError::InvalidInputError(format!(
+                    "{} requires root, which it can't get inside the sandbox",
+                    cmd.name
+                )));
+            }
+            Some(sandbox) => {
+                let writable = sandbox
+                    .writable
+                    .iter()
+                    .map(|dir| {
+                        let expanded = CmdEngine::expand_path(&dir.to_string_lossy())?;
+                        expanded.canonicalize().ok().filter(|dir| dir.is_dir()).ok_or_else(|| {
+                            LiaCoreError::InvalidInputError(format!("Not a directory: {}", expanded.display()))
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let (uid, gid) = match &run_as {
+                    Some(user) => (user.uid, user.gid),
+                    None => unsafe { (libc::getuid(), libc::getgid()) },
+                };
+                let setup = SandboxSetup::new(&writable, &cwd, uid, gid)?;
+                Some((Sandbox { writable }, setup))
+            }
+            None => None,
+        };
 
-        if output.status.success() {
-            Ok(output)
+        let retry = cmd.retry_policy()?.merge(&options.retry);
+        let retry_output = match &retry.on_output {
+            Some(_) if tty => {
+                return Err(LiaCoreError::InvalidInputError(String::from(
+                    "Retrying on output doesn't work on a pseudo-terminal, where LiA doesn't see the output",
+                )));
+            }
+            Some(pattern) => Some(Regex::new(pattern).map_err(|e| {
+                LiaCoreError::InvalidInputError(format!("Invalid retry output pattern: {}", e))
+            })?),
+            None => None,
+        };
+
+        Ok(Self {
+            name: cmd.name.clone(),
+            command_text: cmd.command_text.clone(),
+            interpreter,
+            argv: cmd.argv.clone().filter(|argv| !argv.is_empty()),
+            cwd,
+            env,
+            timeout,
+            tty,
+            stdin: options.stdin.clone(),
+            ok_exit_codes: cmd.ok_exit_codes.clone().unwrap_or_default(),
+            args: options.args.clone(),
+            sudo,
+            run_as,
+            limits,
+            sandbox,
+            secrets: HashMap::new(),
+            retry,
+            retry_output,
+        })
+    }
+
+    /// Program and arguments the command is spawned with.
+    pub fn program_args(&self) -> Vec<String> {
+        let mut program_args = Vec::new();
+        if self.sudo {
+            // sudo prompts on the terminal itself, and resets the environment unless told to keep our variables.
+            program_args.push(String::from("sudo"));
+            if !self.env.is_empty() {
+                let keys: Vec<&str> = self.env.iter().map(|(key, _)| key.as_str()).collect();
+                program_args.push(format!("--preserve-env={}", keys.join(",")));
+            }
+            program_args.push(String::from("--"));
+        }
+
+        if let Some(argv) = &self.argv {
+            program_args.extend(argv.iter().chain(&self.args).cloned());
+            return program_args;
+        }
+
+        program_args.push(self.interpreter.program().to_string());
+        program_args.push(self.interpreter.inline_flag().to_string());
+        if self.args.is_empty() || !self.interpreter.is_posix_shell() {
+            // Python and node see the args in sys.argv/process.argv, fish in $argv.
+            program_args.push(self.command_text.clone());
+            program_args.extend(self.args.iter().cloned());
+        } else if shell::uses_positional_params(&self.command_text) {
+            program_args.push(self.command_text.clone());
+            program_args.push(self.name.clone());
+            program_args.extend(self.args.iter().cloned());
         } else {
-            Err(LiaCoreError::CommandExecutionError(format!(
-                "Command exited with status code {}",
-                output.status.code().unwrap_or(-1)
-            )))
+            program_args.push(format!("{} {}", self.command_text, shell::join(&self.args)));
         }
+        program_args
     }
 
-    pub fn execute_command_stream(
-        command_text: &str,
-        path: &Path,
-        output_tx: tokio::sync::mpsc::UnboundedSender<String>,
+    /// Names of the secrets referenced as `{{secret:name}}` in what would run or in its environment.
+    pub fn secret_names(&self) -> Vec<String> {
+        let env_values = self.env.iter().map(|(_, value)| value);
+        let mut names: Vec<String> = self.program_args().iter().chain(env_values).flat_map(|text| secrets::placeholders(text)).collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    fn mask(&self) -> SecretMask {
+        SecretMask::new(self.secrets.values())
+    }
+
+    /// What would be spawned, without checking or starting anything.
+    pub fn render(&self) -> RenderedCommand {
+        let argv = self.program_args();
+        let (script, interpreter) = match self.argv {
+            Some(_) => (None, None),
+            None => {
+                let script = argv.iter().position(|arg| arg == self.interpreter.inline_flag()).map(|flag| argv[flag + 1].clone());
+                (script, Some(self.interpreter.to_string()))
+            }
+        };
+
+        RenderedCommand {
+            name: self.name.clone(),
+            script,
+            interpreter,
+            argv,
+            cwd: self.cwd.clone(),
+            env: self.env.clone(),
+            timeout: self.timeout,
+            tty: self.tty,
+            ok_exit_codes: self.ok_exit_codes.clone(),
+            limits: self.limits.clone(),
+            sandbox: self.sandbox.as_ref().map(|(sandbox, _)| sandbox.clone()),
+            retry: self.retry.clone(),
+            user: match (&self.run_as, self.sudo) {
+                (Some(user), _) => Some(user.name.clone()),
+                (None, true) => Some(String::from("root")),
+                (None, false) => None,
+            },
+        }
+    }
+}
+
+pub struct CmdEngine;
+
+impl CmdEngine {
+    /// Runs the command to completion and collects its output. On timeout or cancellation it is stopped like a
+    /// streamed run, SIGTERM first, and reaped before the error is returned.
+    pub async fn execute_command(spec: &ExecSpec, cancel: &CancelHandle) -> Result<Output, LiaCoreError> {
+        Self::check_cwd(spec)?;
+
+        let mut command = Self::build_command(spec);
+        let stdin_bytes = Self::configure_stdin(&mut command, &spec.stdin)?;
+        let mut child = command
+            .process_group(0)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Self::spawn_error(spec, e))?;
+        Self::feed_stdin(&mut child, stdin_bytes);
+
+        let stdout_task = tokio::spawn(Self::read_all(child.stdout.take().expect("Failed to capture stdout")));
+        let stderr_task = tokio::spawn(Self::read_all(child.stderr.take().expect("Failed to capture stderr")));
+
+        let waited = tokio::select! {
+            status = child.wait() => Ok(status),
+            _ = Self::timeout_elapsed(spec.timeout) => {
+                Err(LiaCoreError::CommandTimeoutError(spec.timeout.unwrap_or_default()))
+            }
+            _ = cancel.cancelled() => Err(LiaCoreError::CommandCancelledError),
+        };
+        let status = match waited {
+            Ok(status) => status.map_err(LiaCoreError::IoError)?,
+            Err(reason) => {
+                Self::terminate(&mut child).await?;
+                // Whatever it left running in the background may still hold the pipes.
+                stdout_task.abort();
+                stderr_task.abort();
+                return Err(reason);
+            }
+        };
+        let output = Output {
+            status,
+            stdout: stdout_task.await.unwrap_or_default(),
+            stderr: stderr_task.await.unwrap_or_default(),
+        };
+
+        // Left untouched without secrets, since masking needs the output as text.
+        let output = if spec.secrets.is_empty() {
+            output
+        } else {
+            let mask = spec.mask();
+            Output {
+                stdout: mask.apply(&String::from_utf8_lossy(&output.stdout)).into_bytes(),
+                stderr: mask.apply(&String::from_utf8_lossy(&output.stderr)).into_bytes(),
+                ..output
+            }
+        };
+        Self::check_status(spec, output.status)?;
+        Ok(output)
+    }
+
+    /// Streams the command like `execute_command_stream`, or on a pseudo-terminal for `spec.tty`, until an attempt
+    /// succeeds or `spec.retry` gives up. Every attempt ends with `ExecEvent::Exited`; with a retry policy, failed
+    /// ones are followed by `ExecEvent::Retrying` and the run by `ExecEvent::Attempts`.
+    pub async fn execute_with_retries(
+        spec: &ExecSpec,
+        output_tx: UnboundedSender<ExecEvent>,
+        cancel: &CancelHandle,
     ) -> Result<(), LiaCoreError> {
-        if !path.exists() || !path.is_dir() {
-            return Err(LiaCoreError::InvalidInputError(format!(
-                "Path does not exist or is not a directory: {}",
-                path.display()
-            )));
+        if !spec.retry.is_enabled() {
+            return Self::execute_attempt(spec, output_tx, cancel).await;
         }
 
-        let mut child = Command::new("sh")
-            .arg("-c")
-            .arg(command_text)
-            .current_dir(path)
+        let max_attempts = spec.retry.max_attempts();
+        let mut attempts: Vec<Attempt> = Vec::new();
+        loop {
+            // Passed through as they come, watching for the end of the attempt and output worth retrying on.
+            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+            let forward_tx = output_tx.clone();
+            let pattern = spec.retry_output.clone();
+            let forward = tokio::spawn(async move {
+                let (mut matched, mut exited) = (false, None);
+                while let Some(event) = rx.recv().await {
+                    match &event {
+                        ExecEvent::Stdout(line) | ExecEvent::Stderr(line) => {
+                            matched = matched || pattern.as_ref().is_some_and(|pattern| pattern.is_match(line));
+                        }
+                        ExecEvent::Exited { code, signal, duration } => exited = Some((*code, *signal, *duration)),
+                        _ => {}
+                    }
+                    let _ = forward_tx.send(event);
+                }
+                (matched, exited)
+            });
+            let result = Self::execute_attempt(spec, tx, cancel).await;
+            let (matched, exited) = forward.await.unwrap_or_default();
+
+            // An attempt that never started, e.g. for a missing program, would fail the same way again.
+            let Some((code, signal, duration)) = exited else {
+                return result;
+            };
+            let attempt = Attempt {
+                number: attempts.len() as u32 + 1,
+                code,
+                signal,
+                timed_out: matches!(result, Err(LiaCoreError::CommandTimeoutError(_))),
+                succeeded: result.is_ok(),
+                duration,
+            };
+            let retry = match &result {
+                Ok(_) | Err(LiaCoreError::CommandCancelledError) => false,
+                Err(_) => attempt.number < max_attempts && spec.retry.should_retry(attempt.exit_status(), matched),
+            };
+            if !retry {
+                attempts.push(attempt);
+                let _ = output_tx.send(ExecEvent::Attempts(attempts));
+                return result;
+            }
+
+            let delay = spec.retry.delay(attempt.number);
+            let _ = output_tx.send(ExecEvent::Retrying { attempt: attempt.clone(), max_attempts, delay });
+            attempts.push(attempt);
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = cancel.cancelled() => {
+                    let _ = output_tx.send(ExecEvent::Attempts(attempts));
+                    return Err(LiaCoreError::CommandCancelledError);
+                }
+            }
+        }
+    }
+
+    async fn execute_attempt(
+        spec: &ExecSpec,
+        output_tx: UnboundedSender<ExecEvent>,
+        cancel: &CancelHandle,
+    ) -> Result<(), LiaCoreError> {
+        if spec.tty {
+            Self::execute_command_tty(spec, output_tx, cancel).await
+        } else {
+            Self::execute_command_stream(spec, output_tx, cancel).await
+        }
+    }
+
+    /// Streams the command's output line by line as it is produced, then sends a final `ExecEvent::Exited`.
+    /// The run is stopped when `spec.timeout` elapses or `cancel` is triggered.
+    pub async fn execute_command_stream(
+        spec: &ExecSpec,
+        output_tx: UnboundedSender<ExecEvent>,
+        cancel: &CancelHandle,
+    ) -> Result<(), LiaCoreError> {
+        Self::check_cwd(spec)?;
+
+        let started = Instant::now();
+        let mut command = Self::build_command(spec);
+        let stdin_bytes = Self::configure_stdin(&mut command, &spec.stdin)?;
+        let mut child = command
+            .process_group(0)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
-            .map_err(LiaCoreError::IoError)?;
+            .map_err(|e| Self::spawn_error(spec, e))?;
+        Self::feed_stdin(&mut child, stdin_bytes);
 
         let stdout = child.stdout.take().expect("Failed to capture stdout");
         let stderr = child.stderr.take().expect("Failed to capture stderr");
 
-        let stdout_reader = BufReader::new(stdout);
-        let stderr_reader = BufReader::new(stderr);
+        // Both pipes are drained concurrently so a chatty stream can't block the child on the other one.
+        let stdout_task = tokio::spawn(Self::forward_lines(stdout, output_tx.clone(), ExecEvent::Stdout, spec.mask()));
+        let stderr_task = tokio::spawn(Self::forward_lines(stderr, output_tx.clone(), ExecEvent::Stderr, spec.mask()));
 
-        for line in stdout_reader.lines() {
-            if let Ok(line) = line {
-                output_tx.send(line).unwrap_or_else(|_| ());
+        let waited = tokio::select! {
+            status = child.wait() => Ok(status),
+            _ = Self::timeout_elapsed(spec.timeout) => {
+                Err(LiaCoreError::CommandTimeoutError(spec.timeout.unwrap_or_default()))
             }
+            _ = cancel.cancelled() => Err(LiaCoreError::CommandCancelledError),
+        };
+        let (status, interrupted) = match waited {
+            Ok(status) => (status.map_err(LiaCoreError::IoError)?, None),
+            Err(reason) => (Self::terminate(&mut child).await?, Some(reason)),
+        };
+
+        let _ = stdout_task.await;
+        let _ = stderr_task.await;
+
+        Self::finish(spec, status, interrupted, started, &output_tx)
+    }
+
+    /// Runs the command on a pseudo-terminal wired to LiA's own stdin/stdout, for interactive programs.
+    /// Output goes straight to the terminal, secrets masked; only the final `ExecEvent::Exited` is sent on `output_tx`.
+    pub async fn execute_command_tty(
+        spec: &ExecSpec,
+        output_tx: UnboundedSender<ExecEvent>,
+        cancel: &CancelHandle,
+    ) -> Result<(), LiaCoreError> {
+        Self::check_cwd(spec)?;
+
+        let pty = Pty::open().map_err(LiaCoreError::IoError)?;
+        let master_fd = pty.master.as_raw_fd();
+        pty::copy_window_size(libc::STDIN_FILENO, master_fd);
+
+        let mut command = Self::build_command(spec);
+        command
+            .stdin(Stdio::from(pty.slave.try_clone().map_err(LiaCoreError::IoError)?))
+            .stdout(Stdio::from(pty.slave.try_clone().map_err(LiaCoreError::IoError)?))
+            .stderr(Stdio::from(pty.slave.try_clone().map_err(LiaCoreError::IoError)?));
+        // A new session makes the pty the child's controlling terminal, so job control and Ctrl-C work inside it.
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() < 0 || libc::ioctl(0, libc::TIOCSCTTY, 0) < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
         }
 
-        for line in stderr_reader.lines() {
-            if let Ok(line) = line {
-                output_tx.send(format!("ERROR: {}", line)).unwrap_or_else(|_| ());
+        let started = Instant::now();
+        let mut child = command.spawn().map_err(|e| Self::spawn_error(spec, e))?;
+        // Only the child may hold the slave side, otherwise the output pump never sees it close.
+        drop(command);
+        drop(pty.slave);
+
+        let raw_mode = RawModeGuard::enable(libc::STDIN_FILENO).map_err(LiaCoreError::IoError)?;
+        let input_done = Arc::new(AtomicBool::new(false));
+        pty::spawn_input_pump(&pty.master, input_done.clone()).map_err(LiaCoreError::IoError)?;
+        let output_pump = pty::spawn_output_pump(&pty.master, spec.mask()).map_err(LiaCoreError::IoError)?;
+
+        let mut window_changes = signal(SignalKind::window_change()).map_err(LiaCoreError::IoError)?;
+        let mut terminations = signal(SignalKind::terminate()).map_err(LiaCoreError::IoError)?;
+        let mut hangups = signal(SignalKind::hangup()).map_err(LiaCoreError::IoError)?;
+
+        let waited = loop {
+            tokio::select! {
+                status = child.wait() => break Ok(status),
+                _ = Self::timeout_elapsed(spec.timeout) => {
+                    break Err(LiaCoreError::CommandTimeoutError(spec.timeout.unwrap_or_default()));
+                }
+                _ = cancel.cancelled() => break Err(LiaCoreError::CommandCancelledError),
+                _ = window_changes.recv() => pty::copy_window_size(libc::STDIN_FILENO, master_fd),
+                _ = terminations.recv() => Self::forward_signal(&child, libc::SIGTERM),
+                _ = hangups.recv() => Self::forward_signal(&child, libc::SIGHUP),
             }
+        };
+        let (status, interrupted) = match waited {
+            Ok(status) => (status.map_err(LiaCoreError::IoError)?, None),
+            Err(reason) => (Self::terminate(&mut child).await?, Some(reason)),
+        };
+
+        input_done.store(true, Ordering::Relaxed);
+        // Background processes left behind may keep the pty open; don't wait on them forever.
+        let output_deadline = Instant::now() + Duration::from_millis(500);
+        while !output_pump.is_finished() && Instant::now() < output_deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
         }
+        drop(raw_mode);
 
-        let status = child.wait().map_err(LiaCoreError::IoError)?;
+        Self::finish(spec, status, interrupted, started, &output_tx)
+    }
 
-        if status.success() {
-            Ok(())
-        } else {
-            Err(LiaCoreError::CommandExecutionError(format!(
-                "Command exited with status code {}",
-                status.code().unwrap_or(-1)
+    /// Reports how the run ended and turns it into the run's result.
+    fn finish(
+        spec: &ExecSpec,
+        status: ExitStatus,
+        interrupted: Option<LiaCoreError>,
+        started: Instant,
+        output_tx: &UnboundedSender<ExecEvent>,
+    ) -> Result<(), LiaCoreError> {
+        let _ = output_tx.send(ExecEvent::Exited {
+            code: status.code(),
+            signal: status.signal(),
+            duration: started.elapsed(),
+        });
+
+        if let Some(reason) = interrupted {
+            return Err(reason);
+        }
+
+        Self::check_status(spec, status)
+    }
+
+    /// Accepts a zero exit code or one listed in `spec.ok_exit_codes`.
+    fn check_status(spec: &ExecSpec, status: ExitStatus) -> Result<(), LiaCoreError> {
+        let code = status.code();
+        let signal = status.signal();
+        let accepted = code.is_some_and(|code| code == 0 || spec.ok_exit_codes.contains(&code));
+        if accepted {
+            return Ok(());
+        }
+
+        let message = match (code, signal) {
+            (Some(code), _) => format!("Command exited with status code {}", code),
+            (None, Some(signal)) => format!("Command was killed by signal {}", signal),
+            (None, None) => String::from("Command exited with an unknown status"),
+        };
+        Err(LiaCoreError::CommandExecutionError { message, code, signal })
+    }
+
+    /// Wires the child's stdin. Returns the bytes to write once it is spawned, for `StdinSource::Bytes`.
+    fn configure_stdin(
+        command: &mut Command,
+        stdin: &Option<StdinSource>,
+    ) -> Result<Option<Vec<u8>>, LiaCoreError> {
+        match stdin {
+            None => {
+                command.stdin(Stdio::null());
+                Ok(None)
+            }
+            Some(StdinSource::Inherit) => {
+                command.stdin(Stdio::inherit());
+                Ok(None)
+            }
+            Some(StdinSource::File(path)) => {
+                let file = std::fs::File::open(path).map_err(|e| {
+                    LiaCoreError::InvalidInputError(format!(
+                        "Could not open stdin file {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+                command.stdin(Stdio::from(file));
+                Ok(None)
+            }
+            Some(StdinSource::Bytes(bytes)) => {
+                command.stdin(Stdio::piped());
+                Ok(Some(bytes.clone()))
+            }
+        }
+    }
+
+    /// Writes in the background so a child that doesn't read its stdin can't stall the run.
+    fn feed_stdin(child: &mut Child, bytes: Option<Vec<u8>>) {
+        if let (Some(bytes), Some(mut stdin)) = (bytes, child.stdin.take()) {
+            tokio::spawn(async move {
+                // Dropping stdin afterwards closes the pipe, which the child sees as end of input.
+                let _ = stdin.write_all(&bytes).await;
+            });
+        }
+    }
+
+    async fn read_all<R: AsyncRead + Unpin>(mut reader: R) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let _ = reader.read_to_end(&mut buf).await;
+        buf
+    }
+
+    async fn timeout_elapsed(timeout: Option<Duration>) {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Sends SIGTERM to the command's process group, then SIGKILL if it is still running after the grace period.
+    async fn terminate(child: &mut Child) -> Result<ExitStatus, LiaCoreError> {
+        let grace = Duration::from_secs(SysConfigs::get_execution().kill_grace_period_secs);
+
+        if let Some(pid) = child.id() {
+            Self::signal_group(pid, libc::SIGTERM);
+            if let Ok(status) = tokio::time::timeout(grace, child.wait()).await {
+                return status.map_err(LiaCoreError::IoError);
+            }
+            Self::signal_group(pid, libc::SIGKILL);
+        }
+
+        child.wait().await.map_err(LiaCoreError::IoError)
+    }
+
+    fn forward_signal(child: &Child, signal: libc::c_int) {
+        if let Some(pid) = child.id() {
+            Self::signal_group(pid, signal);
+        }
+    }
+
+    /// Starts `program` in a new session, detached from LiA's terminal, so it outlives the caller. Returns its pid.
+    /// `passphrase` is passed in `LIA_SECRET_PASSPHRASE` to this process only; otherwise the variable is cleared.
+    pub fn spawn_detached(program: &str, args: &[String], passphrase: Option<&str>) -> Result<u32, LiaCoreError> {
+        let mut command = std::process::Command::new(program);
+        command.env_remove(PASSPHRASE_ENV);
+        if let Some(passphrase) = passphrase {
+            command.env(PASSPHRASE_ENV, passphrase);
+        }
+        command
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        let mut child = command.spawn().map_err(LiaCoreError::IoError)?;
+        let pid = child.id();
+        // Reap it when it ends, in case the caller is still running by then.
+        std::thread::spawn(move || child.wait());
+        Ok(pid)
+    }
+
+    pub fn signal_process(pid: i32, signal: libc::c_int) -> Result<(), LiaCoreError> {
+        if unsafe { libc::kill(pid, signal) } == -1 {
+            return Err(LiaCoreError::IoError(std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    pub fn is_process_alive(pid: i32) -> bool {
+        // EPERM means the process exists but belongs to another user.
+        let alive = unsafe { libc::kill(pid, 0) } == 0;
+        alive || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+
+    /// Every command is the leader of its own process group, so this reaches anything it spawned too.
+    fn signal_group(pid: u32, signal: libc::c_int) {
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), signal);
+        }
+    }
+
+    /// Sends the output line by line. Secrets are masked before it is split, so multi-line ones are caught too.
+    async fn forward_lines<R: AsyncRead + Unpin>(
+        mut reader: R,
+        output_tx: UnboundedSender<ExecEvent>,
+        event: fn(String) -> ExecEvent,
+        mask: SecretMask,
+    ) {
+        let mut mask = mask.stream();
+        let mut masked = Vec::new();
+        let mut buf = [0u8; 4096];
+        let send = |line: &[u8]| {
+            let line = String::from_utf8_lossy(line);
+            // Keep draining even if nobody listens, otherwise the child could block on a full pipe.
+            let _ = output_tx.send(event(line.trim_end_matches(['\n', '\r']).to_string()));
+        };
+
+        loop {
+            let ended = match reader.read(&mut buf).await {
+                Ok(0) | Err(_) => {
+                    masked.extend(mask.finish());
+                    true
+                }
+                Ok(n) => {
+                    masked.extend(mask.push(&buf[..n]));
+                    false
+                }
+            };
+            while let Some(end) = masked.iter().position(|&byte| byte == b'\n') {
+                send(&masked.drain(..=end).collect::<Vec<u8>>());
+            }
+            if ended {
+                if !masked.is_empty() {
+                    send(&masked);
+                }
+                break;
+            }
+        }
+    }
+
+    /// Expands `~` and `$VAR`/`${VAR}` references in a stored or user-supplied path.
+    pub fn expand_path(path: &str) -> Result<PathBuf, LiaCoreError> {
+        shellexpand::full(path)
+            .map(|expanded| PathBuf::from(expanded.as_ref()))
+            .map_err(|e| LiaCoreError::InvalidInputError(format!(
+                "Could not expand path {}: {}",
+                path, e
             )))
+    }
+
+    fn check_cwd(spec: &ExecSpec) -> Result<(), LiaCoreError> {
+        if !spec.cwd.is_dir() {
+            return Err(LiaCoreError::InvalidInputError(format!(
+                "Path does not exist or is not a directory: {}",
+                spec.cwd.display()
+            )));
+        }
+        Ok(())
+    }
+
+    fn build_command(spec: &ExecSpec) -> Command {
+        // Secrets are only put in at this point, so they never show up in rendered or logged commands.
+        let program_args: Vec<String> = spec.program_args().iter().map(|arg| secrets::substitute(arg, &spec.secrets)).collect();
+        let mut command = Command::new(&program_args[0]);
+        command
+            .args(&program_args[1..])
+            .current_dir(&spec.cwd)
+            .env_remove(PASSPHRASE_ENV)
+            .envs(spec.env.iter().map(|(key, value)| (key, secrets::substitute(value, &spec.secrets))));
+        if !spec.limits.is_empty() {
+            // Before dropping privileges, which may no longer allow them.
+            let limits = spec.limits.clone();
+            unsafe {
+                command.pre_exec(move || Self::apply_limits(&limits));
+            }
         }
+        if let Some(user) = spec.run_as.clone() {
+            // Groups first: once the uid changes, the process can no longer change them.
+            unsafe {
+                command.pre_exec(move || {
+                    if libc::setgroups(user.groups.len(), user.groups.as_ptr()) < 0
+                        || libc::setgid(user.gid) < 0
+                        || libc::setuid(user.uid) < 0
+                    {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+        if let Some((_, mut setup)) = spec.sandbox.clone() {
+            // Last, so the namespaces belong to the user the command runs as.
+            unsafe {
+                command.pre_exec(move || setup.enter());
+            }
+        }
+        command
     }
 
+    /// Applies `limits` to the current process. Runs between fork and exec, so it only makes system calls.
+    fn apply_limits(limits: &ResourceLimits) -> std::io::Result<()> {
+        let rlimits = [
+            // A second past the soft limit, SIGXCPU becomes SIGKILL.
+            (libc::RLIMIT_CPU, limits.cpu_secs.map(|secs| (secs, secs.saturating_add(1)))),
+            (libc::RLIMIT_AS, limits.memory_bytes.map(|bytes| (bytes, bytes))),
+            (libc::RLIMIT_NOFILE, limits.open_files.map(|files| (files, files))),
+            (libc::RLIMIT_NPROC, limits.processes.map(|processes| (processes, processes))),
+        ];
+        let is_root = unsafe { libc::geteuid() } == 0;
+
+        for (resource, limit) in rlimits {
+            let Some((soft, hard)) = limit else { continue };
+            let mut current = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+            if unsafe { libc::getrlimit(resource, &mut current) } < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            // Only root may raise a hard limit; others get as close as they're allowed.
+            let hard = if is_root { hard } else { hard.min(current.rlim_max) };
+            let limit = libc::rlimit { rlim_cur: soft.min(hard), rlim_max: hard };
+            if unsafe { libc::setrlimit(resource, &limit) } < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+
+        if let Some(nice) = limits.nice {
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+
+        if let Some(io_priority) = limits.io_priority {
+            const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+            if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, io_priority.ioprio()) } < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
+    /// The working directory was checked beforehand, so a missing file here means a missing program.
+    fn spawn_error(spec: &ExecSpec, e: std::io::Error) -> LiaCoreError {
+        if spec.sudo && e.kind() == std::io::ErrorKind::NotFound {
+            return LiaCoreError::CommandExecutionError {
+                message: format!("{} must run as root, but sudo is not installed", spec.name),
+                code: None,
+                signal: None,
+            };
+        }
+        match (&spec.argv, e.kind()) {
+            (Some(argv), std::io::ErrorKind::NotFound) => {
+                LiaCoreError::CommandExecutionError {
+                    message: format!("Program not found: {}", argv[0]),
+                    code: None,
+                    signal: None,
+                }
+            }
+            (None, std::io::ErrorKind::NotFound) => {
+                LiaCoreError::InterpreterNotFoundError(spec.interpreter.to_string())
+            }
+            _ if spec.sandbox.is_some() => LiaCoreError::SandboxError(e.to_string()),
+            _ => LiaCoreError::IoError(e),
+        }
+    }
 
     #[cfg(target_os = "linux")]
     pub fn is_sudo_user() -> bool {
@@ -102,4 +875,139 @@ impl CmdEngine {
     pub fn is_sudo_user() -> bool {
         false
     }
+
+    /// The user who gave LiA root rights through sudo (or a setuid binary), if LiA is running with them.
+    pub fn invoking_user() -> Option<RunAsUser> {
+        if unsafe { libc::geteuid() } != 0 {
+            return None;
+        }
+        let uid = match std::env::var("SUDO_UID").ok().and_then(|uid| uid.parse().ok()) {
+            Some(uid) => uid,
+            None => unsafe { libc::getuid() },
+        };
+        if uid == 0 {
+            return None;
+        }
+
+        Self::lookup_user(uid).or_else(|| {
+            // Not in the password database: trust what sudo recorded, but never fall back to root's group.
+            let gid = std::env::var("SUDO_GID").ok().and_then(|gid| gid.parse().ok()).unwrap_or(uid);
+            let name = std::env::var("SUDO_USER").unwrap_or_else(|_| uid.to_string());
+            Some(RunAsUser { uid, gid, groups: vec![gid], name, home: None })
+        })
+    }
+
+    /// Name, groups and home directory of `uid` from the password and group databases.
+    fn lookup_user(uid: u32) -> Option<RunAsUser> {
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut buf = vec![0 as libc::c_char; 16 * 1024];
+        let mut result = std::ptr::null_mut();
+        let found = unsafe { libc::getpwuid_r(uid, &mut passwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+        if found != 0 || result.is_null() {
+            return None;
+        }
+
+        let name = unsafe { CStr::from_ptr(passwd.pw_name) };
+        let home = unsafe { CStr::from_ptr(passwd.pw_dir) };
+        let gid = passwd.pw_gid;
+
+        let mut groups: Vec<libc::gid_t> = vec![0; 64];
+        loop {
+            let mut count = groups.len() as libc::c_int;
+            let listed = unsafe { libc::getgrouplist(name.as_ptr(), gid, groups.as_mut_ptr(), &mut count) };
+            if listed >= 0 {
+                groups.truncate(count as usize);
+                break;
+            }
+            // Too small: `count` now holds the number of groups.
+            groups.resize((count as usize).max(groups.len() * 2), 0);
+        }
+
+        Some(RunAsUser {
+            uid,
+            gid,
+            groups,
+            name: name.to_string_lossy().into_owned(),
+            home: Some(PathBuf::from(home.to_string_lossy().into_owned())).filter(|home| !home.as_os_str().is_empty()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(command_text: &str, argv: Option<&[&str]>, args: &[&str]) -> ExecSpec {
+        ExecSpec {
+            name: String::from("greet"),
+            command_text: command_text.to_string(),
+            interpreter: Interpreter::Bash,
+            argv: argv.map(|argv| argv.iter().map(|s| s.to_string()).collect()),
+            cwd: PathBuf::from("/tmp"),
+            env: Vec::new(),
+            timeout: None,
+            tty: false,
+            stdin: None,
+            ok_exit_codes: Vec::new(),
+            args: args.iter().map(|s| s.to_string()).collect(),
+            sudo: false,
+            run_as: None,
+            limits: ResourceLimits::default(),
+            sandbox: None,
+            secrets: HashMap::new(),
+            retry: RetryPolicy::default(),
+            retry_output: None,
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let rendered = spec("echo hi", None, &["a b"]).render();
+        assert_eq!(rendered.argv, vec!["bash", "-c", "echo hi 'a b'"]);
+        assert_eq!(rendered.script.as_deref(), Some("echo hi 'a b'"));
+        assert_eq!(rendered.interpreter.as_deref(), Some("bash"));
+
+        let rendered = spec("echo \"$1\"", None, &["x"]).render();
+        assert_eq!(rendered.argv, vec!["bash", "-c", "echo \"$1\"", "greet", "x"]);
+
+        let rendered = spec("ls", Some(&["ls", "-l"]), &["/tmp"]).render();
+        assert_eq!(rendered.argv, vec!["ls", "-l", "/tmp"]);
+        assert_eq!(rendered.script, None);
+
+        let mut root = spec("whoami", None, &[]);
+        root.sudo = true;
+        root.env = vec![(String::from("A"), String::from("1")), (String::from("B"), String::from("2"))];
+        let rendered = root.render();
+        assert_eq!(rendered.argv, vec!["sudo", "--preserve-env=A,B", "--", "bash", "-c", "whoami"]);
+        assert_eq!(rendered.script.as_deref(), Some("whoami"));
+        assert_eq!(rendered.user.as_deref(), Some("root"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_command_timeout() {
+        let marker = std::env::temp_dir().join(format!("lia-terminated-{}", std::process::id()));
+        let mut timed = spec(&format!("trap 'touch {}; exit 3' TERM; sleep 5 & wait", marker.display()), None, &[]);
+        timed.timeout = Some(Duration::from_millis(300));
+
+        let started = Instant::now();
+        let result = CmdEngine::execute_command(&timed, &CancelHandle::new()).await;
+        assert!(matches!(result, Err(LiaCoreError::CommandTimeoutError(_))), "{:?}", result);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        // It got SIGTERM and the chance to handle it, rather than an immediate SIGKILL.
+        assert!(marker.exists());
+        std::fs::remove_file(marker).unwrap();
+
+        let cancel = CancelHandle::new();
+        cancel.cancel();
+        let result = CmdEngine::execute_command(&spec("sleep 5", None, &[]), &cancel).await;
+        assert!(matches!(result, Err(LiaCoreError::CommandCancelledError)), "{:?}", result);
+    }
+
+    #[test]
+    fn test_lookup_user() {
+        let root = CmdEngine::lookup_user(0).unwrap();
+        assert_eq!(root.name, "root");
+        assert_eq!(root.gid, 0);
+        assert!(root.groups.contains(&0));
+    }
 }
@@ -1,18 +1,33 @@
 use sqlx::{
-    postgres::PgPoolOptions, 
+    postgres::{PgPoolOptions, PgQueryResult},
+    Connection,
+    Encode,
+    PgConnection,
     PgPool,
+    Postgres,
+    QueryBuilder,
+    Type,
     Error as SqlxError
 };
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 
 use crate::{
-    models::command::{Command, NewCommand, UpdateCommand},
+    models::{
+        command::{Command, NewCommand, UpdateCommand},
+        job::{Job, JobStatus},
+        retry::Attempt,
+        schedule::{CatchUp, NewSchedule, Schedule},
+        secret::{EncryptedSecret, Secret, SecretStore},
+    },
     errors::LiaCoreError
 };
 
 use system::Logger;
 
+/// Arbitrary key for the advisory lock that keeps a single `lia daemon` running per database.
+const DAEMON_LOCK_KEY: i64 = 0x6c6961;
+
 pub struct Database {
     pub pool: PgPool,
 }
@@ -64,8 +79,9 @@ impl Database {
         let now = Utc::now().naive_utc();
         sqlx::query!(
             r#"
-            INSERT INTO commands (id, name, description, command_text, tags, created_at, updated_at)
-            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            INSERT INTO commands (id, name, description, command_text, tags, created_at, updated_at, working_dir, env_vars, interpreter, argv, timeout_ms, tty, ok_exit_codes, confirm, requires_root, cpu_limit_secs, memory_limit_bytes, open_files_limit, processes_limit, nice, io_priority,
+                retry_attempts, retry_delay_ms, retry_backoff, retry_max_delay_ms, retry_on_exit_codes, retry_on_output)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $26, $27, $28)
             "#,
             id,
             new_cmd.name,
@@ -74,10 +90,31 @@ impl Database {
             new_cmd.tags.as_deref(),
             now,
             now,
+            new_cmd.working_dir,
+            new_cmd.env_vars.as_deref(),
+            new_cmd.interpreter,
+            new_cmd.argv.as_deref(),
+            new_cmd.timeout_ms,
+            new_cmd.tty,
+            new_cmd.ok_exit_codes.as_deref(),
+            new_cmd.confirm,
+            new_cmd.requires_root,
+            new_cmd.cpu_limit_secs,
+            new_cmd.memory_limit_bytes,
+            new_cmd.open_files_limit,
+            new_cmd.processes_limit,
+            new_cmd.nice,
+            new_cmd.io_priority,
+            new_cmd.retry_attempts,
+            new_cmd.retry_delay_ms,
+            new_cmd.retry_backoff,
+            new_cmd.retry_max_delay_ms,
+            new_cmd.retry_on_exit_codes.as_deref(),
+            new_cmd.retry_on_output,
         )
         .execute(&self.pool)
         .await
-        .map_err(LiaCoreError::DatabaseError)?;
+        .map_err(|e| Self::map_name_conflict(e, &new_cmd.name))?;
         Ok(())
     }
 
@@ -85,65 +122,74 @@ impl Database {
         &self,
         update_cmd: UpdateCommand,
     ) -> Result<(), LiaCoreError> {
-        let mut tx = self.pool.begin().await.map_err(LiaCoreError::DatabaseError)?;
-    
-        if let Some(tags) = update_cmd.new_tags {
-            sqlx::query!(
-                r#"
-                UPDATE commands
-                SET tags = $1, updated_at = $2
-                WHERE name = $3  -- Use name instead of id
-                "#,
-                &tags,
-                Utc::now().naive_utc(),
-                update_cmd.name
-            )
-            .execute(&mut *tx)
-            .await
-            .map_err(LiaCoreError::DatabaseError)?;
-        }
-    
-        if let Some(description) = update_cmd.new_description {
-            sqlx::query!(
-                r#"
-                UPDATE commands
-                SET description = $1, updated_at = $2
-                WHERE name = $3  -- Use name instead of id
-                "#,
-                description,
-                Utc::now().naive_utc(),
-                update_cmd.name
-            )
-            .execute(&mut *tx)
-            .await
-            .map_err(LiaCoreError::DatabaseError)?;
-        }
-    
-        if let Some(command_text) = update_cmd.new_command_text {
-            sqlx::query!(
-                r#"
-                UPDATE commands
-                SET command_text = $1, updated_at = $2
-                WHERE name = $3  -- Use name instead of id
-                "#,
-                command_text,
-                Utc::now().naive_utc(),
-                update_cmd.name
-            )
-            .execute(&mut *tx)
-            .await
-            .map_err(LiaCoreError::DatabaseError)?;
+        // Resolve aliases up front so the update matches on the id.
+        let cmd = self.get_command_by_name(&update_cmd.name).await?;
+
+        // Settings given as "", 0 or an empty list are cleared.
+        let mut query = QueryBuilder::<Postgres>::new("UPDATE commands SET updated_at = ");
+        query.push_bind(Utc::now().naive_utc());
+        push_set(&mut query, "tags", update_cmd.new_tags);
+        push_set(&mut query, "description", update_cmd.new_description);
+        push_set(&mut query, "command_text", update_cmd.new_command_text);
+        push_set(&mut query, "working_dir", cleared(update_cmd.new_working_dir));
+        push_set(&mut query, "env_vars", update_cmd.new_env_vars);
+        push_set(&mut query, "interpreter", cleared(update_cmd.new_interpreter));
+        push_set(&mut query, "argv", cleared(update_cmd.new_argv));
+        push_set(&mut query, "timeout_ms", cleared(update_cmd.new_timeout_ms));
+        push_set(&mut query, "tty", update_cmd.new_tty);
+        push_set(&mut query, "ok_exit_codes", cleared(update_cmd.new_ok_exit_codes));
+        push_set(&mut query, "confirm", update_cmd.new_confirm);
+        push_set(&mut query, "requires_root", update_cmd.new_requires_root);
+        push_set(&mut query, "cpu_limit_secs", cleared(update_cmd.new_cpu_limit_secs));
+        push_set(&mut query, "memory_limit_bytes", cleared(update_cmd.new_memory_limit_bytes));
+        push_set(&mut query, "open_files_limit", cleared(update_cmd.new_open_files_limit));
+        push_set(&mut query, "processes_limit", cleared(update_cmd.new_processes_limit));
+        push_set(&mut query, "nice", cleared(update_cmd.new_nice));
+        push_set(&mut query, "io_priority", cleared(update_cmd.new_io_priority));
+        push_set(&mut query, "retry_attempts", cleared(update_cmd.new_retry_attempts));
+        push_set(&mut query, "retry_delay_ms", cleared(update_cmd.new_retry_delay_ms));
+        push_set(&mut query, "retry_backoff", cleared(update_cmd.new_retry_backoff));
+        push_set(&mut query, "retry_max_delay_ms", cleared(update_cmd.new_retry_max_delay_ms));
+        push_set(&mut query, "retry_on_exit_codes", cleared(update_cmd.new_retry_on_exit_codes));
+        push_set(&mut query, "retry_on_output", cleared(update_cmd.new_retry_on_output));
+        query.push(" WHERE id = ").push_bind(cmd.id);
+
+        let result = query.build().execute(&self.pool).await.map_err(LiaCoreError::DatabaseError)?;
+        Self::check_command_found(result, &cmd.name)
+    }
+
+    /// Maps a statement on a resolved command that touched no rows (e.g. it was deleted meanwhile) to `CommandNotFoundError`.
+    fn check_command_found(result: PgQueryResult, name: &str) -> Result<(), LiaCoreError> {
+        if result.rows_affected() == 0 {
+            return Err(LiaCoreError::CommandNotFoundError(name.to_string()));
         }
-    
-        tx.commit().await.map_err(LiaCoreError::DatabaseError)?;
         Ok(())
-    }    
+    }
 
-    pub async fn get_all_commands(&self, limit: i64, offset: i64) -> Result<Vec<Command>, LiaCoreError> {
-        let rows = sqlx::query_as!(
+    /// Loads the commands with these ids, in the same order. Lookups select ids and load them here, so the
+    /// column list lives in one query.
+    async fn get_commands_by_ids(&self, ids: &[Uuid]) -> Result<Vec<Command>, LiaCoreError> {
+        sqlx::query_as!(
             Command,
             r#"
-            SELECT id, name, description, command_text, tags, created_at, updated_at
+            SELECT id, name, description, command_text, tags, created_at, updated_at, working_dir, env_vars, interpreter, argv, timeout_ms, tty, ok_exit_codes, confirm, requires_root, cpu_limit_secs, memory_limit_bytes, open_files_limit, processes_limit, nice, io_priority,
+                retry_attempts, retry_delay_ms, retry_backoff, retry_max_delay_ms, retry_on_exit_codes, retry_on_output,
+                ARRAY(SELECT alias FROM command_aliases WHERE command_id = commands.id ORDER BY alias) AS "aliases!"
+            FROM commands
+            WHERE id = ANY($1)
+            ORDER BY array_position($1, id)
+            "#,
+            ids
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)
+    }
+
+    pub async fn get_all_commands(&self, limit: i64, offset: i64) -> Result<Vec<Command>, LiaCoreError> {
+        let ids = sqlx::query_scalar!(
+            r#"
+            SELECT id
             FROM commands
             ORDER BY name
             LIMIT $1 OFFSET $2
@@ -154,26 +200,83 @@ impl Database {
         .fetch_all(&self.pool)
         .await
         .map_err(LiaCoreError::DatabaseError)?;
-        Ok(rows)
+        self.get_commands_by_ids(&ids).await
     }
 
     pub async fn get_command_by_name(&self, cmd_name: &str) -> Result<Command, LiaCoreError> {
-        let result = sqlx::query_as!(
-            Command,
+        let id = sqlx::query_scalar!(
             r#"
-            SELECT id, name, description, command_text, tags, created_at, updated_at
+            SELECT id
             FROM commands
             WHERE name = $1
+                OR id = (SELECT command_id FROM command_aliases WHERE alias = $1)
             "#,
             cmd_name
         )
-        .fetch_one(&self.pool)
-        .await;
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
 
-        match result {
-            Ok(cmd) => Ok(cmd),
-            Err(SqlxError::RowNotFound) => Err(LiaCoreError::CommandNotFoundError(cmd_name.to_string())),
-            Err(e) => Err(LiaCoreError::DatabaseError(e)),
+        let not_found = || LiaCoreError::CommandNotFoundError(cmd_name.to_string());
+        let id = id.ok_or_else(not_found)?;
+        self.get_commands_by_ids(&[id]).await?.pop().ok_or_else(not_found)
+    }
+
+    pub async fn add_aliases(&self, cmd_name: &str, aliases: &[String]) -> Result<(), LiaCoreError> {
+        let cmd = self.get_command_by_name(cmd_name).await?;
+        let now = Utc::now().naive_utc();
+        let mut tx = self.pool.begin().await.map_err(LiaCoreError::DatabaseError)?;
+
+        for alias in aliases {
+            sqlx::query!(
+                r#"
+                INSERT INTO command_aliases (alias, command_id, created_at)
+                VALUES ($1, $2, $3)
+                "#,
+                alias,
+                cmd.id,
+                now,
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Self::map_name_conflict(e, alias))?;
+        }
+
+        tx.commit().await.map_err(LiaCoreError::DatabaseError)?;
+        Ok(())
+    }
+
+    pub async fn remove_aliases(&self, aliases: &[String]) -> Result<(), LiaCoreError> {
+        let mut tx = self.pool.begin().await.map_err(LiaCoreError::DatabaseError)?;
+
+        for alias in aliases {
+            let result = sqlx::query!(
+                r#"
+                DELETE FROM command_aliases
+                WHERE alias = $1
+                "#,
+                alias,
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(LiaCoreError::DatabaseError)?;
+
+            if result.rows_affected() == 0 {
+                return Err(LiaCoreError::InvalidInputError(format!("Alias not found: {}", alias)));
+            }
+        }
+
+        tx.commit().await.map_err(LiaCoreError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Names and aliases share one namespace; turns the database's unique violation into a readable error.
+    fn map_name_conflict(e: SqlxError, name: &str) -> LiaCoreError {
+        match &e {
+            SqlxError::Database(db_err) if db_err.is_unique_violation() => {
+                LiaCoreError::NameConflictError(name.to_string())
+            }
+            _ => LiaCoreError::DatabaseError(e),
         }
     }
 
@@ -190,12 +293,11 @@ impl Database {
             None
         };
     
-        let commands = match (search_query, tags) {
+        let ids = match (search_query, tags) {
             (Some(sq), Some(tags_vec)) => {
-                sqlx::query_as!(
-                    Command,
+                sqlx::query_scalar!(
                     r#"
-                    SELECT id, name, description, command_text, tags, created_at, updated_at
+                    SELECT id
                     FROM commands
                     WHERE
                         search_vector @@ to_tsquery('english', $1)
@@ -213,10 +315,9 @@ impl Database {
                 .map_err(LiaCoreError::DatabaseError)?
             }
             (Some(sq), None) => {
-                sqlx::query_as!(
-                    Command,
+                sqlx::query_scalar!(
                     r#"
-                    SELECT id, name, description, command_text, tags, created_at, updated_at
+                    SELECT id
                     FROM commands
                     WHERE
                         search_vector @@ to_tsquery('english', $1)
@@ -232,10 +333,9 @@ impl Database {
                 .map_err(LiaCoreError::DatabaseError)?
             }
             (None, Some(tags_vec)) => {
-                sqlx::query_as!(
-                    Command,
+                sqlx::query_scalar!(
                     r#"
-                    SELECT id, name, description, command_text, tags, created_at, updated_at
+                    SELECT id
                     FROM commands
                     WHERE
                         tags && $1::text[]
@@ -250,24 +350,9 @@ impl Database {
                 .await
                 .map_err(LiaCoreError::DatabaseError)?
             }
-            (None, None) => {
-                sqlx::query_as!(
-                    Command,
-                    r#"
-                    SELECT id, name, description, command_text, tags, created_at, updated_at
-                    FROM commands
-                    ORDER BY name
-                    LIMIT $1 OFFSET $2
-                    "#,
-                    limit,
-                    offset
-                )
-                .fetch_all(&self.pool)
-                .await
-                .map_err(LiaCoreError::DatabaseError)?
-            }
+            (None, None) => return self.get_all_commands(limit, offset).await,
         };
-        Ok(commands)
+        self.get_commands_by_ids(&ids).await
     }    
 
     pub async fn find_commands_for_deletion(
@@ -276,43 +361,18 @@ impl Database {
         tags: Option<Vec<String>>,
     ) -> Result<Vec<Command>, LiaCoreError> {
         match (name, tags) {
-            (Some(name), Some(tags_vec)) => {
-                sqlx::query_as!(
-                    Command,
-                    r#"
-                    SELECT id, name, description, command_text, tags, created_at, updated_at
-                    FROM commands
-                    WHERE
-                        name = $1
-                        AND tags && $2::text[]
-                    "#,
-                    name,
-                    &tags_vec,
-                )
-                .fetch_all(&self.pool)
-                .await
-                .map_err(LiaCoreError::DatabaseError)
-            }
-            (Some(name), None) => {
-                sqlx::query_as!(
-                    Command,
-                    r#"
-                    SELECT id, name, description, command_text, tags, created_at, updated_at
-                    FROM commands
-                    WHERE
-                        name = $1
-                    "#,
-                    name,
-                )
-                .fetch_all(&self.pool)
-                .await
-                .map_err(LiaCoreError::DatabaseError)
-            }
+            (Some(name), tags) => match self.get_command_by_name(&name).await {
+                Ok(cmd) => {
+                    let tagged = tags.is_none_or(|tags| cmd.tags.iter().flatten().any(|tag| tags.contains(tag)));
+                    Ok(if tagged { vec![cmd] } else { vec![] })
+                }
+                Err(LiaCoreError::CommandNotFoundError(_)) => Ok(vec![]),
+                Err(e) => Err(e),
+            },
             (None, Some(tags_vec)) => {
-                sqlx::query_as!(
-                    Command,
+                let ids = sqlx::query_scalar!(
                     r#"
-                    SELECT id, name, description, command_text, tags, created_at, updated_at
+                    SELECT id
                     FROM commands
                     WHERE
                         tags && $1::text[]
@@ -321,7 +381,8 @@ impl Database {
                 )
                 .fetch_all(&self.pool)
                 .await
-                .map_err(LiaCoreError::DatabaseError)
+                .map_err(LiaCoreError::DatabaseError)?;
+                self.get_commands_by_ids(&ids).await
             }
             (None, None) => {
                 Ok(vec![])
@@ -334,34 +395,40 @@ impl Database {
         name: Option<String>,
         tags: Option<Vec<String>>,
     ) -> Result<(), LiaCoreError> {
-        match (name, tags) {
-            (Some(name), Some(tags_vec)) => {
-                sqlx::query!(
+        let cmd = match name {
+            Some(name) => Some(self.get_command_by_name(&name).await?),
+            None => None,
+        };
+        match (cmd, tags) {
+            (Some(cmd), Some(tags_vec)) => {
+                let result = sqlx::query!(
                     r#"
                     DELETE FROM commands
                     WHERE
-                        name = $1
+                        id = $1
                         AND tags && $2::text[]
                     "#,
-                    name,
+                    cmd.id,
                     &tags_vec,
                 )
                 .execute(&self.pool)
                 .await
                 .map_err(LiaCoreError::DatabaseError)?;
+                Self::check_command_found(result, &cmd.name)?;
             }
-            (Some(name), None) => {
-                sqlx::query!(
+            (Some(cmd), None) => {
+                let result = sqlx::query!(
                     r#"
                     DELETE FROM commands
                     WHERE
-                        name = $1
+                        id = $1
                     "#,
-                    name,
+                    cmd.id,
                 )
                 .execute(&self.pool)
                 .await
                 .map_err(LiaCoreError::DatabaseError)?;
+                Self::check_command_found(result, &cmd.name)?;
             }
             (None, Some(tags_vec)) => {
                 sqlx::query!(
@@ -388,4 +455,457 @@ impl Database {
             .map_err(LiaCoreError::DatabaseError)?;
         Ok(())
     }
+
+    /// Records a running job whose output goes to `<log_dir>/<id>.log`.
+    pub async fn add_job(
+        &self,
+        command_name: &str,
+        run_options: &str,
+        log_dir: &str,
+        schedule_id: Option<i32>,
+        scheduled_for: Option<NaiveDateTime>,
+    ) -> Result<Job, LiaCoreError> {
+        let mut tx = self.pool.begin().await.map_err(LiaCoreError::DatabaseError)?;
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO jobs (command_name, status, log_path, run_options, started_at, schedule_id, scheduled_for)
+            VALUES ($1, $2, '', $3, $4, $5, $6)
+            RETURNING id
+            "#,
+            command_name,
+            JobStatus::Running.as_str(),
+            run_options,
+            Utc::now().naive_utc(),
+            schedule_id,
+            scheduled_for,
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+
+        sqlx::query!(
+            r#"
+            UPDATE jobs
+            SET log_path = $1
+            WHERE id = $2
+            "#,
+            format!("{}/{}.log", log_dir, id),
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+
+        tx.commit().await.map_err(LiaCoreError::DatabaseError)?;
+        self.get_job(id).await
+    }
+
+    pub async fn set_job_pid(&self, id: i32, pid: i32) -> Result<(), LiaCoreError> {
+        sqlx::query!(
+            r#"
+            UPDATE jobs
+            SET pid = $1
+            WHERE id = $2
+            "#,
+            pid,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Records how a running job ended. A job that already finished is left as is.
+    pub async fn finish_job(
+        &self,
+        id: i32,
+        status: JobStatus,
+        exit_code: Option<i32>,
+        signal: Option<i32>,
+    ) -> Result<(), LiaCoreError> {
+        sqlx::query!(
+            r#"
+            UPDATE jobs
+            SET status = $1, exit_code = $2, signal = $3, finished_at = $4
+            WHERE id = $5 AND status = $6
+            "#,
+            status.as_str(),
+            exit_code,
+            signal,
+            Utc::now().naive_utc(),
+            id,
+            JobStatus::Running.as_str(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+        Ok(())
+    }
+
+    pub async fn get_job(&self, id: i32) -> Result<Job, LiaCoreError> {
+        let result = sqlx::query_as!(
+            Job,
+            r#"
+            SELECT id, command_name, status AS "status: JobStatus", pid, exit_code, signal, log_path, run_options, started_at, finished_at,
+                schedule_id, scheduled_for
+            FROM jobs
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match result {
+            Ok(job) => Ok(job),
+            Err(SqlxError::RowNotFound) => Err(LiaCoreError::JobNotFoundError(id)),
+            Err(e) => Err(LiaCoreError::DatabaseError(e)),
+        }
+    }
+
+    pub async fn get_jobs(&self, limit: i64) -> Result<Vec<Job>, LiaCoreError> {
+        let rows = sqlx::query_as!(
+            Job,
+            r#"
+            SELECT id, command_name, status AS "status: JobStatus", pid, exit_code, signal, log_path, run_options, started_at, finished_at,
+                schedule_id, scheduled_for
+            FROM jobs
+            ORDER BY id DESC
+            LIMIT $1
+            "#,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+        Ok(rows)
+    }
+
+    pub async fn add_job_attempt(&self, job_id: i32, attempt: &Attempt) -> Result<(), LiaCoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO job_attempts (job_id, attempt, exit_code, signal, timed_out, succeeded, duration_ms, finished_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            job_id,
+            attempt.number as i32,
+            attempt.code,
+            attempt.signal,
+            attempt.timed_out,
+            attempt.succeeded,
+            attempt.duration.as_millis() as i64,
+            Utc::now().naive_utc(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+        Ok(())
+    }
+
+    pub async fn get_job_attempts(&self, job_id: i32) -> Result<Vec<Attempt>, LiaCoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT attempt, exit_code, signal, timed_out, succeeded, duration_ms
+            FROM job_attempts
+            WHERE job_id = $1
+            ORDER BY attempt
+            "#,
+            job_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+        Ok(rows
+            .into_iter()
+            .map(|row| Attempt {
+                number: row.attempt as u32,
+                code: row.exit_code,
+                signal: row.signal,
+                timed_out: row.timed_out,
+                succeeded: row.succeeded,
+                duration: std::time::Duration::from_millis(row.duration_ms as u64),
+            })
+            .collect())
+    }
+
+    /// Jobs started by a schedule, newest first.
+    pub async fn get_schedule_jobs(&self, schedule_id: i32, limit: i64) -> Result<Vec<Job>, LiaCoreError> {
+        let rows = sqlx::query_as!(
+            Job,
+            r#"
+            SELECT id, command_name, status AS "status: JobStatus", pid, exit_code, signal, log_path, run_options, started_at, finished_at,
+                schedule_id, scheduled_for
+            FROM jobs
+            WHERE schedule_id = $1
+            ORDER BY id DESC
+            LIMIT $2
+            "#,
+            schedule_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+        Ok(rows)
+    }
+
+    pub async fn add_schedule(&self, new_schedule: NewSchedule, next_run_at: NaiveDateTime) -> Result<Schedule, LiaCoreError> {
+        let cmd = self.get_command_by_name(&new_schedule.command_name).await?;
+
+        let id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO schedules (command_id, cron_expr, interval_secs, catch_up, next_run_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id
+            "#,
+            cmd.id,
+            new_schedule.cron_expr,
+            new_schedule.interval_secs,
+            new_schedule.catch_up.as_str(),
+            next_run_at,
+            Utc::now().naive_utc(),
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+
+        self.get_schedule(id).await
+    }
+
+    pub async fn get_schedule(&self, id: i32) -> Result<Schedule, LiaCoreError> {
+        let result = sqlx::query_as!(
+            Schedule,
+            r#"
+            SELECT s.id, s.command_id, c.name AS "command_name!", s.cron_expr, s.interval_secs,
+                s.catch_up AS "catch_up: CatchUp", s.next_run_at, s.last_run_at, s.created_at
+            FROM schedules s
+            JOIN commands c ON c.id = s.command_id
+            WHERE s.id = $1
+            "#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await;
+
+        match result {
+            Ok(schedule) => Ok(schedule),
+            Err(SqlxError::RowNotFound) => Err(LiaCoreError::ScheduleNotFoundError(id)),
+            Err(e) => Err(LiaCoreError::DatabaseError(e)),
+        }
+    }
+
+    pub async fn get_schedules(&self) -> Result<Vec<Schedule>, LiaCoreError> {
+        let rows = sqlx::query_as!(
+            Schedule,
+            r#"
+            SELECT s.id, s.command_id, c.name AS "command_name!", s.cron_expr, s.interval_secs,
+                s.catch_up AS "catch_up: CatchUp", s.next_run_at, s.last_run_at, s.created_at
+            FROM schedules s
+            JOIN commands c ON c.id = s.command_id
+            ORDER BY s.id
+            "#
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+        Ok(rows)
+    }
+
+    /// Schedules whose next run is at or before `now`, oldest first.
+    pub async fn get_due_schedules(&self, now: NaiveDateTime) -> Result<Vec<Schedule>, LiaCoreError> {
+        let rows = sqlx::query_as!(
+            Schedule,
+            r#"
+            SELECT s.id, s.command_id, c.name AS "command_name!", s.cron_expr, s.interval_secs,
+                s.catch_up AS "catch_up: CatchUp", s.next_run_at, s.last_run_at, s.created_at
+            FROM schedules s
+            JOIN commands c ON c.id = s.command_id
+            WHERE s.next_run_at <= $1
+            ORDER BY s.next_run_at
+            "#,
+            now
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+        Ok(rows)
+    }
+
+    pub async fn set_schedule_runs(
+        &self,
+        id: i32,
+        next_run_at: NaiveDateTime,
+        last_run_at: Option<NaiveDateTime>,
+    ) -> Result<(), LiaCoreError> {
+        sqlx::query!(
+            r#"
+            UPDATE schedules
+            SET next_run_at = $1, last_run_at = $2
+            WHERE id = $3
+            "#,
+            next_run_at,
+            last_run_at,
+            id
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+        Ok(())
+    }
+
+    pub async fn remove_schedule(&self, id: i32) -> Result<(), LiaCoreError> {
+        let result = sqlx::query!("DELETE FROM schedules WHERE id = $1", id)
+            .execute(&self.pool)
+            .await
+            .map_err(LiaCoreError::DatabaseError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(LiaCoreError::ScheduleNotFoundError(id));
+        }
+        Ok(())
+    }
+
+    pub async fn get_secret_store(&self) -> Result<Option<SecretStore>, LiaCoreError> {
+        sqlx::query_as!(SecretStore, "SELECT salt, iterations, verifier FROM secret_store")
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(LiaCoreError::DatabaseError)
+    }
+
+    pub async fn create_secret_store(&self, store: &SecretStore) -> Result<(), LiaCoreError> {
+        sqlx::query!(
+            "INSERT INTO secret_store (salt, iterations, verifier, created_at) VALUES ($1, $2, $3, $4)",
+            store.salt,
+            store.iterations,
+            store.verifier,
+            Utc::now().naive_utc(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Adds the secret, or replaces its value if it exists.
+    pub async fn set_secret(&self, name: &str, secret: &EncryptedSecret) -> Result<(), LiaCoreError> {
+        let now = Utc::now().naive_utc();
+        sqlx::query!(
+            r#"
+            INSERT INTO secrets (name, nonce, ciphertext, tag, created_at, updated_at)
+            VALUES ($1, $2, $3, $4, $5, $5)
+            ON CONFLICT (name) DO UPDATE
+            SET nonce = EXCLUDED.nonce, ciphertext = EXCLUDED.ciphertext, tag = EXCLUDED.tag, updated_at = EXCLUDED.updated_at
+            "#,
+            name,
+            secret.nonce,
+            secret.ciphertext,
+            secret.tag,
+            now,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(LiaCoreError::DatabaseError)?;
+        Ok(())
+    }
+
+    pub async fn get_encrypted_secrets(&self) -> Result<Vec<(String, EncryptedSecret)>, LiaCoreError> {
+        let rows = sqlx::query!("SELECT name, nonce, ciphertext, tag FROM secrets")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(LiaCoreError::DatabaseError)?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.name, EncryptedSecret { nonce: row.nonce, ciphertext: row.ciphertext, tag: row.tag }))
+            .collect())
+    }
+
+    pub async fn get_secrets(&self) -> Result<Vec<Secret>, LiaCoreError> {
+        sqlx::query_as!(Secret, "SELECT name, created_at, updated_at FROM secrets ORDER BY name")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(LiaCoreError::DatabaseError)
+    }
+
+    pub async fn remove_secret(&self, name: &str) -> Result<(), LiaCoreError> {
+        let result = sqlx::query!("DELETE FROM secrets WHERE name = $1", name)
+            .execute(&self.pool)
+            .await
+            .map_err(LiaCoreError::DatabaseError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(LiaCoreError::SecretNotFoundError(name.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Takes the daemon's advisory lock on a dedicated connection. The lock is released when the connection is
+    /// dropped, so keep it for as long as the daemon runs.
+    pub async fn lock_daemon(&self) -> Result<PgConnection, LiaCoreError> {
+        let mut conn = self.pool.acquire().await.map_err(LiaCoreError::DatabaseError)?.detach();
+        let locked = sqlx::query_scalar!("SELECT pg_try_advisory_lock($1)", DAEMON_LOCK_KEY)
+            .fetch_one(&mut conn)
+            .await
+            .map_err(LiaCoreError::DatabaseError)?;
+
+        if locked != Some(true) {
+            let _ = conn.close().await;
+            return Err(LiaCoreError::DaemonAlreadyRunningError);
+        }
+        Ok(conn)
+    }
+}
+
+/// Adds `, <column> = <value>` to an UPDATE's SET list when a value is given.
+fn push_set<'a, T>(query: &mut QueryBuilder<'a, Postgres>, column: &str, value: Option<T>)
+where
+    T: 'a + Encode<'a, Postgres> + Type<Postgres>,
+{
+    if let Some(value) = value {
+        query.push(format!(", {} = ", column)).push_bind(value);
+    }
+}
+
+/// Turns a default value ("", 0 or an empty list) into NULL, which clears the setting.
+fn cleared<T: Default + PartialEq>(value: Option<T>) -> Option<Option<T>> {
+    value.map(|value| Some(value).filter(|value| *value != T::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use system::EnvConfig;
+
+    #[tokio::test]
+    async fn test_update_and_delete_by_alias() {
+        // The query macros already need this database to compile, so it's there when tests build.
+        let db = Database::new(&EnvConfig::get_database_url()).await.unwrap();
+        let name = format!("lia-test-alias-{}", std::process::id());
+        let alias = format!("{}-a", name);
+        db.add_command(NewCommand { name: name.clone(), command_text: String::from("true"), ..Default::default() })
+            .await
+            .unwrap();
+        db.add_aliases(&name, std::slice::from_ref(&alias)).await.unwrap();
+
+        db.update_command(UpdateCommand {
+            name: alias.clone(),
+            new_description: Some(String::from("via alias")),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+        let cmd = db.get_command_by_name(&name).await.unwrap();
+        assert_eq!(cmd.description.as_deref(), Some("via alias"));
+
+        let found = db.find_commands_for_deletion(Some(alias.clone()), None).await.unwrap();
+        assert_eq!(found.iter().map(|c| c.id).collect::<Vec<_>>(), vec![cmd.id]);
+        db.delete_commands(Some(alias.clone()), None).await.unwrap();
+        assert!(matches!(db.get_command_by_name(&name).await, Err(LiaCoreError::CommandNotFoundError(_))));
+
+        let missing = UpdateCommand { name: alias.clone(), new_description: Some(String::new()), ..Default::default() };
+        assert!(matches!(db.update_command(missing).await, Err(LiaCoreError::CommandNotFoundError(_))));
+        assert!(db.find_commands_for_deletion(Some(alias.clone()), None).await.unwrap().is_empty());
+        assert!(matches!(db.delete_commands(Some(alias), None).await, Err(LiaCoreError::CommandNotFoundError(_))));
+    }
 }
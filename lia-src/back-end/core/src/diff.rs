@@ -0,0 +1,35 @@
+use similar::{capture_diff_slices, Algorithm, DiffOp};
+
+/// For each line of `current`, whether it is new or changed compared to `previous`.
+pub fn changed_lines(previous: &[String], current: &[String]) -> Vec<bool> {
+    let mut changed = vec![false; current.len()];
+
+    for op in capture_diff_slices(Algorithm::Myers, previous, current) {
+        match op {
+            DiffOp::Insert { new_index, new_len, .. } | DiffOp::Replace { new_index, new_len, .. } => {
+                changed[new_index..new_index + new_len].fill(true);
+            }
+            DiffOp::Equal { .. } | DiffOp::Delete { .. } => {}
+        }
+    }
+
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_changed_lines() {
+        let previous = lines("a\nb\nc\nd");
+        assert_eq!(changed_lines(&previous, &previous), vec![false; 4]);
+        assert_eq!(changed_lines(&previous, &lines("a\nB\nc\nd\ne")), vec![false, true, false, false, true]);
+        assert_eq!(changed_lines(&previous, &lines("a\nd")), vec![false, false]);
+        assert_eq!(changed_lines(&[], &lines("x")), vec![true]);
+    }
+}
@@ -0,0 +1,53 @@
+use std::path::{Path, PathBuf};
+
+use crate::errors::LiaCoreError;
+
+/// Expands `~`, environment variables and glob patterns in `patterns` to the directories they match, sorted
+/// and without duplicates. With a `marker`, only directories containing it are kept, e.g. `.git` for repositories.
+pub fn find_dirs(patterns: &[String], marker: Option<&str>) -> Result<Vec<PathBuf>, LiaCoreError> {
+    let mut dirs = Vec::new();
+
+    for pattern in patterns {
+        let expanded = shellexpand::full(pattern).map_err(|e| {
+            LiaCoreError::InvalidInputError(format!("Invalid directory pattern '{}': {}", pattern, e))
+        })?;
+        let paths = glob::glob(&expanded).map_err(|e| {
+            LiaCoreError::InvalidInputError(format!("Invalid directory pattern '{}': {}", pattern, e))
+        })?;
+        // Entries that can't be read are skipped rather than failing the whole run.
+        dirs.extend(paths.flatten().filter(|path| path.is_dir() && has_marker(path, marker)));
+    }
+
+    dirs.sort();
+    dirs.dedup();
+    Ok(dirs)
+}
+
+fn has_marker(dir: &Path, marker: Option<&str>) -> bool {
+    match marker {
+        Some(marker) => dir.join(marker).exists(),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_dirs() {
+        let root = std::env::temp_dir().join(format!("lia-find-dirs-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("api/.git")).unwrap();
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("notes.txt"), "").unwrap();
+        let pattern = format!("{}/*", root.display());
+
+        let all = find_dirs(&[pattern.clone(), pattern.clone()], None).unwrap();
+        let repos = find_dirs(&[pattern], Some(".git")).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(all, vec![root.join("api"), root.join("docs")]);
+        assert_eq!(repos, vec![root.join("api")]);
+        assert!(find_dirs(&[String::from("[")], None).is_err());
+    }
+}
@@ -2,8 +2,35 @@ use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum LiaCoreError {
-    #[error("Command execution error: {0}")]
-    CommandExecutionError(String),
+    #[error("Command execution error: {message}")]
+    CommandExecutionError {
+        message: String,
+        /// Exit code of the command, when it ran and exited normally.
+        code: Option<i32>,
+        /// Signal that terminated the command, when it was killed.
+        signal: Option<i32>,
+    },
+
+    #[error("Command timed out after {0:?}")]
+    CommandTimeoutError(std::time::Duration),
+
+    #[error("Command was cancelled")]
+    CommandCancelledError,
+
+    #[error("{0} must run as root: run LiA as root, or set execution.elevate_with_sudo in configs.json")]
+    RootRequiredError(String),
+
+    #[error("Secret not found: {0}")]
+    SecretNotFoundError(String),
+
+    #[error("Secret store error: {0}")]
+    SecretError(String),
+
+    #[error("Failed to set up the sandbox: {0} (are unprivileged user namespaces enabled?)")]
+    SandboxError(String),
+
+    #[error("Interpreter not found: {0} (is it installed and on PATH?)")]
+    InterpreterNotFoundError(String),
 
     #[error("Database error: {0}")]
     DatabaseError(#[from] sqlx::Error),
@@ -11,6 +38,21 @@ pub enum LiaCoreError {
     #[error("Command not found: {0}")]
     CommandNotFoundError(String),
 
+    #[error("Job not found: {0}")]
+    JobNotFoundError(i32),
+
+    #[error("Schedule not found: {0}")]
+    ScheduleNotFoundError(i32),
+
+    #[error("Another lia daemon is already running")]
+    DaemonAlreadyRunningError,
+
+    #[error("Failed to watch files: {0}")]
+    WatchError(String),
+
+    #[error("Name is already used by a command or alias: {0}")]
+    NameConflictError(String),
+
     #[error("Failed to start the database container: {0}")]
     DatabaseContainerError(String),
 
@@ -26,3 +68,31 @@ pub enum LiaCoreError {
     #[error("Failed to run migrations: {0}")]
     MigrationError(#[from] sqlx::migrate::MigrateError),
 }
+
+impl LiaCoreError {
+    /// Exit status a shell would report for this error: the command's own code, or 128+signal if it was killed.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LiaCoreError::CommandExecutionError { code: Some(code), .. } => *code,
+            LiaCoreError::CommandExecutionError { signal: Some(signal), .. } => 128 + signal,
+            LiaCoreError::CommandTimeoutError(_) => 124,
+            LiaCoreError::CommandCancelledError => 130,
+            _ => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code() {
+        let exited = LiaCoreError::CommandExecutionError { message: String::new(), code: Some(3), signal: None };
+        let killed = LiaCoreError::CommandExecutionError { message: String::new(), code: None, signal: Some(9) };
+        assert_eq!(exited.exit_code(), 3);
+        assert_eq!(killed.exit_code(), 137);
+        assert_eq!(LiaCoreError::CommandCancelledError.exit_code(), 130);
+        assert_eq!(LiaCoreError::CommandNotFoundError(String::from("x")).exit_code(), 1);
+    }
+}
@@ -1,6 +1,14 @@
 pub mod models;
 mod db;
+pub mod diff;
+pub mod dirs;
 pub mod errors;
+pub mod risk;
+mod sandbox;
+pub mod secrets;
+pub mod shell;
+pub mod watcher;
 mod cmd_engine;
+mod pty;
 mod api;
 pub use api::LiaCore;
@@ -1,8 +1,15 @@
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::NaiveDateTime;
 
-#[derive(Serialize, Deserialize, Debug)]
+use crate::{
+    errors::LiaCoreError,
+    models::{limits::{IoPriority, ResourceLimits}, retry::{Backoff, RetryPolicy}},
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Command {
     pub id: Uuid,
     pub name: String,
@@ -11,20 +18,117 @@ pub struct Command {
     pub tags: Option<Vec<String>>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub aliases: Vec<String>,
+    pub working_dir: Option<String>,
+    pub env_vars: Option<Vec<String>>,
+    pub interpreter: Option<String>,
+    pub argv: Option<Vec<String>>,
+    pub timeout_ms: Option<i64>,
+    pub tty: bool,
+    pub ok_exit_codes: Option<Vec<i32>>,
+    /// Ask before every run.
+    pub confirm: bool,
+    /// Run as root, through `sudo` when LiA itself isn't root.
+    pub requires_root: bool,
+    pub cpu_limit_secs: Option<i64>,
+    pub memory_limit_bytes: Option<i64>,
+    pub open_files_limit: Option<i64>,
+    pub processes_limit: Option<i64>,
+    pub nice: Option<i32>,
+    /// An `IoPriority` as text, e.g. `best-effort:7`.
+    pub io_priority: Option<String>,
+    /// Total attempts, the first one included.
+    pub retry_attempts: Option<i32>,
+    pub retry_delay_ms: Option<i64>,
+    /// A `Backoff` as text: `fixed` or `exponential`.
+    pub retry_backoff: Option<String>,
+    pub retry_max_delay_ms: Option<i64>,
+    pub retry_on_exit_codes: Option<Vec<i32>>,
+    pub retry_on_output: Option<String>,
+}
+
+impl Command {
+    /// The stored resource limits; zero counts as unset.
+    pub fn resource_limits(&self) -> Result<ResourceLimits, LiaCoreError> {
+        let positive = |value: Option<i64>| value.and_then(|value| u64::try_from(value).ok()).filter(|value| *value > 0);
+        Ok(ResourceLimits {
+            cpu_secs: positive(self.cpu_limit_secs),
+            memory_bytes: positive(self.memory_limit_bytes),
+            open_files: positive(self.open_files_limit),
+            processes: positive(self.processes_limit),
+            nice: self.nice,
+            io_priority: self.io_priority.as_deref().map(IoPriority::parse).transpose()?,
+        })
+    }
+
+    /// The stored retry policy; zero counts as unset.
+    pub fn retry_policy(&self) -> Result<RetryPolicy, LiaCoreError> {
+        let duration = |ms: Option<i64>| ms.and_then(|ms| u64::try_from(ms).ok()).filter(|ms| *ms > 0).map(Duration::from_millis);
+        Ok(RetryPolicy {
+            attempts: self.retry_attempts.and_then(|attempts| u32::try_from(attempts).ok()).filter(|attempts| *attempts > 0),
+            delay: duration(self.retry_delay_ms),
+            backoff: self.retry_backoff.as_deref().map(Backoff::parse).transpose()?,
+            max_delay: duration(self.retry_max_delay_ms),
+            on_exit_codes: self.retry_on_exit_codes.clone().unwrap_or_default(),
+            on_output: self.retry_on_output.clone(),
+        })
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct NewCommand {
     pub name: String,
     pub description: Option<String>,
     pub command_text: String,
     pub tags: Option<Vec<String>>,
+    pub working_dir: Option<String>,
+    pub env_vars: Option<Vec<String>>,
+    pub interpreter: Option<String>,
+    pub argv: Option<Vec<String>>,
+    pub timeout_ms: Option<i64>,
+    pub tty: bool,
+    pub ok_exit_codes: Option<Vec<i32>>,
+    pub confirm: bool,
+    pub requires_root: bool,
+    pub cpu_limit_secs: Option<i64>,
+    pub memory_limit_bytes: Option<i64>,
+    pub open_files_limit: Option<i64>,
+    pub processes_limit: Option<i64>,
+    pub nice: Option<i32>,
+    pub io_priority: Option<String>,
+    pub retry_attempts: Option<i32>,
+    pub retry_delay_ms: Option<i64>,
+    pub retry_backoff: Option<String>,
+    pub retry_max_delay_ms: Option<i64>,
+    pub retry_on_exit_codes: Option<Vec<i32>>,
+    pub retry_on_output: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct UpdateCommand {
     pub name: String,
     pub new_tags: Option<Vec<String>>,
     pub new_description: Option<String>,
     pub new_command_text: Option<String>,
+    pub new_working_dir: Option<String>,
+    pub new_env_vars: Option<Vec<String>>,
+    pub new_interpreter: Option<String>,
+    pub new_argv: Option<Vec<String>>,
+    pub new_timeout_ms: Option<i64>,
+    pub new_tty: Option<bool>,
+    pub new_ok_exit_codes: Option<Vec<i32>>,
+    pub new_confirm: Option<bool>,
+    pub new_requires_root: Option<bool>,
+    pub new_cpu_limit_secs: Option<i64>,
+    pub new_memory_limit_bytes: Option<i64>,
+    pub new_open_files_limit: Option<i64>,
+    pub new_processes_limit: Option<i64>,
+    pub new_nice: Option<i32>,
+    pub new_io_priority: Option<String>,
+    pub new_retry_attempts: Option<i32>,
+    pub new_retry_delay_ms: Option<i64>,
+    pub new_retry_backoff: Option<String>,
+    pub new_retry_max_delay_ms: Option<i64>,
+    pub new_retry_on_exit_codes: Option<Vec<i32>>,
+    pub new_retry_on_output: Option<String>,
 }
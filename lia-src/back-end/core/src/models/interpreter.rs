@@ -0,0 +1,76 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Program that receives a stored command's text.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Interpreter {
+    Sh,
+    Bash,
+    Zsh,
+    Fish,
+    Python3,
+    Node,
+    /// Any other program or path; it is invoked as `<path> -c <command_text>`.
+    Custom(String),
+}
+
+impl Interpreter {
+    pub fn parse(name: &str) -> Interpreter {
+        match name.trim() {
+            "sh" => Interpreter::Sh,
+            "bash" => Interpreter::Bash,
+            "zsh" => Interpreter::Zsh,
+            "fish" => Interpreter::Fish,
+            "python3" => Interpreter::Python3,
+            "node" => Interpreter::Node,
+            other => Interpreter::Custom(other.to_string()),
+        }
+    }
+
+    pub fn program(&self) -> &str {
+        match self {
+            Interpreter::Sh => "sh",
+            Interpreter::Bash => "bash",
+            Interpreter::Zsh => "zsh",
+            Interpreter::Fish => "fish",
+            Interpreter::Python3 => "python3",
+            Interpreter::Node => "node",
+            Interpreter::Custom(path) => path,
+        }
+    }
+
+    /// Flag that makes the program evaluate its next argument as source code.
+    pub fn inline_flag(&self) -> &str {
+        match self {
+            Interpreter::Node => "-e",
+            _ => "-c",
+        }
+    }
+
+    /// Whether `-c <text> <name> <args>...` binds `<name>` to `$0` and the args to `$1..$n`, like `sh` does.
+    /// Custom interpreters are assumed to be shells.
+    pub fn is_posix_shell(&self) -> bool {
+        matches!(self, Interpreter::Sh | Interpreter::Bash | Interpreter::Zsh | Interpreter::Custom(_))
+    }
+}
+
+impl fmt::Display for Interpreter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.program())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Interpreter::parse("bash"), Interpreter::Bash);
+        assert_eq!(Interpreter::parse(" python3 "), Interpreter::Python3);
+        assert_eq!(Interpreter::parse("/opt/bin/ksh"), Interpreter::Custom("/opt/bin/ksh".to_string()));
+        assert_eq!(Interpreter::parse("node").inline_flag(), "-e");
+        assert_eq!(Interpreter::parse("fish").to_string(), "fish");
+    }
+}
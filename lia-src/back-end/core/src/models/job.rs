@@ -0,0 +1,72 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// A command started in the background with `lia run --detach`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Job {
+    pub id: i32,
+    pub command_name: String,
+    pub status: JobStatus,
+    /// Pid of the supervisor process, which runs the command in its own process group.
+    pub pid: Option<i32>,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    /// File receiving the command's stdout and stderr.
+    pub log_path: String,
+    /// The `RunOptions` of the job, as JSON.
+    pub run_options: String,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+    /// Set when the job was started by `lia daemon` for a schedule.
+    pub schedule_id: Option<i32>,
+    /// When the schedule wanted the run to start; `started_at` is when it actually did.
+    pub scheduled_for: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Succeeded,
+    Failed,
+    TimedOut,
+    Killed,
+    /// The supervisor disappeared without recording how the command ended, e.g. after a reboot.
+    Lost,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Running => "running",
+            JobStatus::Succeeded => "succeeded",
+            JobStatus::Failed => "failed",
+            JobStatus::TimedOut => "timed_out",
+            JobStatus::Killed => "killed",
+            JobStatus::Lost => "lost",
+        }
+    }
+}
+
+impl Job {
+    pub fn is_finished(&self) -> bool {
+        self.status != JobStatus::Running
+    }
+
+    /// Exit status a shell would report for the job, following the same rules as `lia run`.
+    pub fn exit_status(&self) -> Option<i32> {
+        match self.status {
+            JobStatus::Running => None,
+            JobStatus::Succeeded => Some(0),
+            JobStatus::TimedOut => Some(124),
+            JobStatus::Lost => Some(1),
+            // Reported as killed by SIGTERM even if the command caught it and exited cleanly.
+            JobStatus::Killed => Some(128 + self.signal.unwrap_or(libc::SIGTERM)),
+            JobStatus::Failed => Some(match (self.exit_code, self.signal) {
+                (Some(code), _) => code,
+                (None, Some(signal)) => 128 + signal,
+                (None, None) => 1,
+            }),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::LiaCoreError;
+
+/// Caps applied to a command's process right before it starts. Unset fields keep LiA's own limits.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct ResourceLimits {
+    /// CPU time in seconds (`RLIMIT_CPU`). The command gets `SIGXCPU` when it runs out, then `SIGKILL` a second later.
+    pub cpu_secs: Option<u64>,
+    /// Address space in bytes (`RLIMIT_AS`); allocations beyond it fail.
+    pub memory_bytes: Option<u64>,
+    /// Open file descriptors (`RLIMIT_NOFILE`).
+    pub open_files: Option<u64>,
+    /// Processes of the user the command runs as, including ones started elsewhere (`RLIMIT_NPROC`).
+    pub processes: Option<u64>,
+    /// Scheduling niceness, from -20 (favoured; needs root) to 19.
+    pub nice: Option<i32>,
+    pub io_priority: Option<IoPriority>,
+}
+
+impl ResourceLimits {
+    /// These limits with any set in `overrides` taking their place.
+    pub fn merge(&self, overrides: &ResourceLimits) -> ResourceLimits {
+        ResourceLimits {
+            cpu_secs: overrides.cpu_secs.or(self.cpu_secs),
+            memory_bytes: overrides.memory_bytes.or(self.memory_bytes),
+            open_files: overrides.open_files.or(self.open_files),
+            processes: overrides.processes.or(self.processes),
+            nice: overrides.nice.or(self.nice),
+            io_priority: overrides.io_priority.or(self.io_priority),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == ResourceLimits::default()
+    }
+}
+
+impl fmt::Display for ResourceLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(secs) = self.cpu_secs {
+            parts.push(format!("cpu {}s", secs));
+        }
+        if let Some(bytes) = self.memory_bytes {
+            parts.push(format!("memory {}", format_size(bytes)));
+        }
+        if let Some(files) = self.open_files {
+            parts.push(format!("open files {}", files));
+        }
+        if let Some(processes) = self.processes {
+            parts.push(format!("processes {}", processes));
+        }
+        if let Some(nice) = self.nice {
+            parts.push(format!("nice {}", nice));
+        }
+        if let Some(io_priority) = self.io_priority {
+            parts.push(format!("ionice {}", io_priority));
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// I/O scheduling class and level, as set by ionice(1). Lower levels get more I/O within a class.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum IoPriority {
+    /// Only gets disk time when no other process needs it.
+    Idle,
+    BestEffort(u8),
+    /// Served before everything else; needs root.
+    Realtime(u8),
+}
+
+impl IoPriority {
+    /// Parses `idle`, `best-effort[:0-7]` or `realtime[:0-7]`; the level defaults to 4.
+    pub fn parse(text: &str) -> Result<IoPriority, LiaCoreError> {
+        let invalid = || {
+            LiaCoreError::InvalidInputError(format!(
+                "Invalid I/O priority: {} (expected idle, best-effort[:0-7] or realtime[:0-7])",
+                text
+            ))
+        };
+        let (class, level) = match text.trim().split_once(':') {
+            Some((class, level)) => (class, Some(level.parse::<u8>().ok().filter(|level| *level <= 7).ok_or_else(invalid)?)),
+            None => (text.trim(), None),
+        };
+        match (class, level) {
+            ("idle", None) => Ok(IoPriority::Idle),
+            ("best-effort", level) => Ok(IoPriority::BestEffort(level.unwrap_or(4))),
+            ("realtime", level) => Ok(IoPriority::Realtime(level.unwrap_or(4))),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Value for the `ioprio_set` system call: the class in the top bits, the level below.
+    pub fn ioprio(&self) -> i32 {
+        const CLASS_SHIFT: i32 = 13;
+        match self {
+            IoPriority::Realtime(level) => (1 << CLASS_SHIFT) | *level as i32,
+            IoPriority::BestEffort(level) => (2 << CLASS_SHIFT) | *level as i32,
+            IoPriority::Idle => 3 << CLASS_SHIFT,
+        }
+    }
+}
+
+impl fmt::Display for IoPriority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IoPriority::Idle => write!(f, "idle"),
+            IoPriority::BestEffort(level) => write!(f, "best-effort:{}", level),
+            IoPriority::Realtime(level) => write!(f, "realtime:{}", level),
+        }
+    }
+}
+
+const SIZE_UNITS: [(&str, u64); 4] = [("T", 1 << 40), ("G", 1 << 30), ("M", 1 << 20), ("K", 1 << 10)];
+
+/// Parses a byte count with an optional binary unit: `4096`, `512K`, `256M`, `1.5G`, `2T` (`MB`, `MiB` also work).
+pub fn parse_size(text: &str) -> Result<u64, LiaCoreError> {
+    let invalid = || LiaCoreError::InvalidInputError(format!("Invalid size: {} (e.g. 512M or 2G)", text));
+    let upper = text.trim().to_uppercase();
+    let digits = upper.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit = upper[digits.len()..].trim_end_matches("IB").trim_end_matches('B');
+
+    let multiplier = match unit {
+        "" => 1,
+        unit => SIZE_UNITS.iter().find(|(name, _)| *name == unit).map(|(_, size)| *size).ok_or_else(invalid)?,
+    };
+    let value: f64 = digits.trim().parse().map_err(|_| invalid())?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(invalid());
+    }
+    Ok((value * multiplier as f64) as u64)
+}
+
+/// Formats a byte count with the largest binary unit that divides it, e.g. `512M`.
+pub fn format_size(bytes: u64) -> String {
+    SIZE_UNITS
+        .iter()
+        .find(|(_, size)| bytes >= *size && bytes.is_multiple_of(*size))
+        .map(|(name, size)| format!("{}{}", bytes / size, name))
+        .unwrap_or_else(|| bytes.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert_eq!(parse_size("512M").unwrap(), 512 << 20);
+        assert_eq!(parse_size("1.5g").unwrap(), 3 << 29);
+        assert_eq!(parse_size("2GiB").unwrap(), 2 << 30);
+        assert_eq!(format_size(512 << 20), "512M");
+        assert_eq!(format_size(1000), "1000");
+        assert!(parse_size("12X").is_err());
+        assert!(parse_size("-1M").is_err());
+    }
+
+    #[test]
+    fn test_io_priority() {
+        assert_eq!(IoPriority::parse("idle").unwrap(), IoPriority::Idle);
+        assert_eq!(IoPriority::parse("best-effort").unwrap(), IoPriority::BestEffort(4));
+        assert_eq!(IoPriority::parse("realtime:0").unwrap().ioprio(), 1 << 13);
+        assert_eq!(IoPriority::parse(&IoPriority::BestEffort(7).to_string()).unwrap(), IoPriority::BestEffort(7));
+        assert!(IoPriority::parse("idle:3").is_err());
+        assert!(IoPriority::parse("best-effort:8").is_err());
+    }
+
+    #[test]
+    fn test_merge() {
+        let stored = ResourceLimits { cpu_secs: Some(60), nice: Some(10), ..ResourceLimits::default() };
+        let run = ResourceLimits { nice: Some(5), ..ResourceLimits::default() };
+        let merged = stored.merge(&run);
+        assert_eq!(merged.cpu_secs, Some(60));
+        assert_eq!(merged.nice, Some(5));
+        assert_eq!(merged.to_string(), "cpu 60s, nice 5");
+    }
+}
@@ -1 +1,8 @@
-pub mod command;
\ No newline at end of file
+pub mod command;
+pub mod interpreter;
+pub mod job;
+pub mod limits;
+pub mod retry;
+pub mod run;
+pub mod schedule;
+pub mod secret;
\ No newline at end of file
@@ -0,0 +1,240 @@
+use std::{fmt, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::LiaCoreError;
+
+/// Wait before the first retry when the policy doesn't set one.
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Longest wait between attempts with exponential backoff, unless the policy sets another.
+pub const DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+/// When and how often a failed run is tried again. Unset fields fall back to the defaults above.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts, the first one included. One or unset means no retries.
+    pub attempts: Option<u32>,
+    /// Wait before the first retry.
+    pub delay: Option<Duration>,
+    pub backoff: Option<Backoff>,
+    /// Cap on the wait with exponential backoff.
+    pub max_delay: Option<Duration>,
+    /// Only retry attempts that exit with one of these statuses (124 for timeouts, 128+n for signal n).
+    pub on_exit_codes: Vec<i32>,
+    /// Only retry attempts with a line of output matching this regex.
+    pub on_output: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Backoff {
+    /// The same wait before every retry.
+    Fixed,
+    /// The wait doubles after every retry, up to `max_delay`.
+    Exponential,
+}
+
+impl Backoff {
+    pub fn parse(text: &str) -> Result<Backoff, LiaCoreError> {
+        match text.trim() {
+            "fixed" => Ok(Backoff::Fixed),
+            "exponential" => Ok(Backoff::Exponential),
+            _ => Err(LiaCoreError::InvalidInputError(format!(
+                "Invalid backoff: {} (expected fixed or exponential)",
+                text
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Backoff::Fixed => "fixed",
+            Backoff::Exponential => "exponential",
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// This policy with any settings in `overrides` taking their place.
+    pub fn merge(&self, overrides: &RetryPolicy) -> RetryPolicy {
+        RetryPolicy {
+            attempts: overrides.attempts.or(self.attempts),
+            delay: overrides.delay.or(self.delay),
+            backoff: overrides.backoff.or(self.backoff),
+            max_delay: overrides.max_delay.or(self.max_delay),
+            on_exit_codes: if overrides.on_exit_codes.is_empty() {
+                self.on_exit_codes.clone()
+            } else {
+                overrides.on_exit_codes.clone()
+            },
+            on_output: overrides.on_output.clone().or_else(|| self.on_output.clone()),
+        }
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.attempts.unwrap_or(1).max(1)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_attempts() > 1
+    }
+
+    /// Wait before attempt `retry + 1`, i.e. after the `retry`th failure.
+    pub fn delay(&self, retry: u32) -> Duration {
+        let delay = self.delay.unwrap_or(DEFAULT_RETRY_DELAY);
+        match self.backoff.unwrap_or(Backoff::Fixed) {
+            Backoff::Fixed => delay,
+            Backoff::Exponential => {
+                let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+                delay.saturating_mul(factor).min(self.max_delay.unwrap_or(DEFAULT_MAX_RETRY_DELAY))
+            }
+        }
+    }
+
+    /// Whether a failed attempt that exited with `exit_status` is worth another try. Without conditions every
+    /// failure is; with both, meeting either is enough.
+    pub fn should_retry(&self, exit_status: i32, output_matched: bool) -> bool {
+        if self.on_exit_codes.is_empty() && self.on_output.is_none() {
+            return true;
+        }
+        self.on_exit_codes.contains(&exit_status) || output_matched
+    }
+}
+
+impl fmt::Display for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let backoff = self.backoff.unwrap_or(Backoff::Fixed);
+        let delay = self.delay.unwrap_or(DEFAULT_RETRY_DELAY);
+        write!(f, "{} attempts, {} backoff from {:?}", self.max_attempts(), backoff.as_str(), delay)?;
+        if backoff == Backoff::Exponential {
+            write!(f, " up to {:?}", self.max_delay.unwrap_or(DEFAULT_MAX_RETRY_DELAY))?;
+        }
+        if !self.on_exit_codes.is_empty() {
+            let codes: Vec<String> = self.on_exit_codes.iter().map(|code| code.to_string()).collect();
+            write!(f, ", on exit codes {}", codes.join(","))?;
+        }
+        if let Some(pattern) = &self.on_output {
+            write!(f, ", on output /{}/", pattern)?;
+        }
+        Ok(())
+    }
+}
+
+/// How one attempt of a run with a retry policy ended.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Attempt {
+    /// Starts at 1.
+    pub number: u32,
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+    pub timed_out: bool,
+    pub succeeded: bool,
+    pub duration: Duration,
+}
+
+impl Attempt {
+    /// Exit status a shell would report for the attempt, following the same rules as `lia run`.
+    pub fn exit_status(&self) -> i32 {
+        match (self.timed_out, self.code, self.signal) {
+            (true, _, _) => 124,
+            (false, Some(code), _) => code,
+            (false, None, Some(signal)) => 128 + signal,
+            (false, None, None) => 1,
+        }
+    }
+}
+
+impl fmt::Display for Attempt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.timed_out, self.code, self.signal) {
+            (true, _, _) => write!(f, "timed out"),
+            (false, Some(code), _) => write!(f, "exit {}", code),
+            (false, None, Some(signal)) => write!(f, "killed by signal {}", signal),
+            (false, None, None) => write!(f, "unknown status"),
+        }
+    }
+}
+
+/// One line per attempt, e.g. `attempt 2 of 3: exit 1 after 1.5s`, for the summary at the end of a run.
+pub fn summarize(attempts: &[Attempt]) -> Vec<String> {
+    attempts
+        .iter()
+        .map(|attempt| {
+            let duration = Duration::from_millis(attempt.duration.as_millis() as u64);
+            let outcome = if attempt.succeeded && attempt.code != Some(0) {
+                format!("{} (accepted)", attempt)
+            } else {
+                attempt.to_string()
+            };
+            format!("attempt {} of {}: {} after {:?}", attempt.number, attempts.len(), outcome, duration)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay() {
+        let fixed = RetryPolicy { attempts: Some(3), delay: Some(Duration::from_secs(2)), ..RetryPolicy::default() };
+        assert_eq!(fixed.delay(1), Duration::from_secs(2));
+        assert_eq!(fixed.delay(4), Duration::from_secs(2));
+
+        let exponential = RetryPolicy {
+            backoff: Some(Backoff::Exponential),
+            max_delay: Some(Duration::from_secs(10)),
+            ..fixed
+        };
+        assert_eq!(exponential.delay(1), Duration::from_secs(2));
+        assert_eq!(exponential.delay(2), Duration::from_secs(4));
+        assert_eq!(exponential.delay(3), Duration::from_secs(8));
+        assert_eq!(exponential.delay(4), Duration::from_secs(10));
+        assert_eq!(exponential.delay(100), Duration::from_secs(10));
+        assert_eq!(exponential.to_string(), "3 attempts, exponential backoff from 2s up to 10s");
+    }
+
+    #[test]
+    fn test_should_retry() {
+        let any = RetryPolicy { attempts: Some(3), ..RetryPolicy::default() };
+        assert!(any.should_retry(1, false));
+
+        let conditional = RetryPolicy {
+            on_exit_codes: vec![75, 124],
+            on_output: Some(String::from("Connection reset")),
+            ..any
+        };
+        assert!(conditional.should_retry(124, false));
+        assert!(conditional.should_retry(1, true));
+        assert!(!conditional.should_retry(1, false));
+    }
+
+    #[test]
+    fn test_merge() {
+        let stored = RetryPolicy { attempts: Some(5), on_exit_codes: vec![1], ..RetryPolicy::default() };
+        let run = RetryPolicy { attempts: Some(2), backoff: Some(Backoff::Exponential), ..RetryPolicy::default() };
+        let merged = stored.merge(&run);
+        assert_eq!(merged.max_attempts(), 2);
+        assert_eq!(merged.backoff, Some(Backoff::Exponential));
+        assert_eq!(merged.on_exit_codes, vec![1]);
+        assert!(!RetryPolicy::default().is_enabled());
+    }
+
+    #[test]
+    fn test_summarize() {
+        let failed = Attempt {
+            number: 1,
+            code: None,
+            signal: Some(9),
+            timed_out: true,
+            succeeded: false,
+            duration: Duration::from_micros(2_000_400),
+        };
+        let succeeded = Attempt { number: 2, code: Some(0), signal: None, timed_out: false, succeeded: true, duration: Duration::from_millis(40) };
+        assert_eq!(failed.exit_status(), 124);
+        assert_eq!(
+            summarize(&[failed, succeeded]),
+            vec!["attempt 1 of 2: timed out after 2s", "attempt 2 of 2: exit 0 after 40ms"]
+        );
+    }
+}
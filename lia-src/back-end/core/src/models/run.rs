@@ -0,0 +1,187 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::{errors::LiaCoreError, models::{limits::ResourceLimits, retry::{Attempt, RetryPolicy}}};
+
+/// Per-run settings that take precedence over what is stored with the command.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct RunOptions {
+    /// Working directory for this run. Falls back to the command's `working_dir`, then the caller's directory.
+    pub cwd: Option<PathBuf>,
+    /// Variables set on top of the command's `env_vars`.
+    pub env: Vec<(String, String)>,
+    /// Time limit for this run, overriding the command's `timeout_ms`.
+    pub timeout: Option<Duration>,
+    /// Force pseudo-terminal execution even if the command isn't stored with `tty`.
+    pub tty: bool,
+    /// What the command reads on stdin. `None` gives it an empty stdin (`/dev/null`).
+    /// Ignored on a pseudo-terminal, which always reads from LiA's terminal.
+    pub stdin: Option<StdinSource>,
+    /// Extra arguments for this run. Appended to argv-mode commands; in shell mode they fill `$1..$n`/`$@`
+    /// when the command references them, and are otherwise appended to the text, quoted.
+    pub args: Vec<String>,
+    /// Resource limits for this run, replacing the command's stored ones where set.
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    #[serde(default)]
+    pub sandbox: Option<Sandbox>,
+    /// Retry settings for this run, replacing the command's stored ones where set.
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+
+/// Isolation for commands that aren't trusted yet: they run in new user, mount and network namespaces, so they
+/// have no network and see the filesystem read-only.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Sandbox {
+    /// Directories the command may still write to, with everything below them.
+    pub writable: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum StdinSource {
+    /// Share the caller's stdin, e.g. to let `cat data.json | lia run pretty-json` work.
+    Inherit,
+    Bytes(Vec<u8>),
+    File(PathBuf),
+}
+
+/// A run resolved against the stored command and its options, as it would be spawned. Used to preview runs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RenderedCommand {
+    pub name: String,
+    /// Source handed to the interpreter, with appended arguments; `None` for commands stored with `argv`.
+    pub script: Option<String>,
+    /// Interpreter running `script`; `None` for commands stored with `argv`, which run directly.
+    pub interpreter: Option<String>,
+    /// Program and arguments that would be spawned.
+    pub argv: Vec<String>,
+    pub cwd: PathBuf,
+    /// Variables set on top of the caller's environment, stored ones first.
+    pub env: Vec<(String, String)>,
+    pub timeout: Option<Duration>,
+    pub tty: bool,
+    pub ok_exit_codes: Vec<i32>,
+    pub limits: ResourceLimits,
+    /// Set for sandboxed runs, with the writable directories resolved.
+    pub sandbox: Option<Sandbox>,
+    pub retry: RetryPolicy,
+    /// User the command runs as when it differs from LiA's: root through `sudo`, or the invoking user when LiA
+    /// runs under sudo.
+    pub user: Option<String>,
+}
+
+/// Output and lifecycle events streamed while a command runs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ExecEvent {
+    Stdout(String),
+    Stderr(String),
+    /// Ends every attempt of a run. `signal` is set when the process was killed by a signal.
+    Exited {
+        code: Option<i32>,
+        signal: Option<i32>,
+        duration: Duration,
+    },
+    /// An attempt failed and the retry policy allows another, which starts after `delay`.
+    Retrying {
+        attempt: Attempt,
+        max_attempts: u32,
+        delay: Duration,
+    },
+    /// The last event of a run with a retry policy: how every attempt ended.
+    Attempts(Vec<Attempt>),
+}
+
+/// Cloneable handle that stops a running command: SIGTERM to its process group, then SIGKILL after the grace period.
+#[derive(Clone, Debug)]
+pub struct CancelHandle {
+    cancelled: Arc<watch::Sender<bool>>,
+}
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        let (cancelled, _) = watch::channel(false);
+        Self { cancelled: Arc::new(cancelled) }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancelled.borrow()
+    }
+
+    /// Resolves once `cancel` has been called on any clone of this handle.
+    pub async fn cancelled(&self) {
+        let mut rx = self.cancelled.subscribe();
+        let _ = rx.wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+impl Default for CancelHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A command started in the background by `LiaCore::start_command_stream`.
+pub struct RunHandle {
+    cancel: CancelHandle,
+    task: JoinHandle<Result<(), LiaCoreError>>,
+}
+
+impl RunHandle {
+    pub(crate) fn new(cancel: CancelHandle, task: JoinHandle<Result<(), LiaCoreError>>) -> Self {
+        Self { cancel, task }
+    }
+
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Waits for the command to finish. Interrupted runs end with `CommandTimeoutError` or `CommandCancelledError`.
+    pub async fn wait(self) -> Result<(), LiaCoreError> {
+        self.task.await.map_err(|e| {
+            LiaCoreError::CommandExecutionError {
+                message: format!("Run task failed: {}", e),
+                code: None,
+                signal: None,
+            }
+        })?
+    }
+}
+
+/// Parses a `KEY=VALUE` pair as accepted by `env_vars` and `--env`.
+pub fn parse_env_var(pair: &str) -> Result<(String, String), LiaCoreError> {
+    match pair.split_once('=') {
+        Some((key, value)) if !key.is_empty() && !key.contains(char::is_whitespace) => {
+            Ok((key.to_string(), value.to_string()))
+        }
+        _ => Err(LiaCoreError::InvalidInputError(format!(
+            "Expected KEY=VALUE, got: {}",
+            pair
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_var() {
+        assert_eq!(parse_env_var("KEY=value").unwrap(), ("KEY".to_string(), "value".to_string()));
+        assert_eq!(parse_env_var("KEY=a=b").unwrap(), ("KEY".to_string(), "a=b".to_string()));
+        assert_eq!(parse_env_var("EMPTY=").unwrap(), ("EMPTY".to_string(), String::new()));
+        assert!(parse_env_var("NOVALUE").is_err());
+        assert!(parse_env_var("=value").is_err());
+        assert!(parse_env_var("BAD KEY=value").is_err());
+    }
+}
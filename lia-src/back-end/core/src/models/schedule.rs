@@ -0,0 +1,128 @@
+use chrono::{Local, NaiveDateTime, TimeZone};
+use croner::Cron;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::errors::LiaCoreError;
+
+/// A stored command run by `lia daemon` on a cron expression or a fixed interval.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Schedule {
+    pub id: i32,
+    pub command_id: Uuid,
+    pub command_name: String,
+    /// Standard 5-field cron expression, evaluated in local time.
+    pub cron_expr: Option<String>,
+    pub interval_secs: Option<i64>,
+    pub catch_up: CatchUp,
+    pub next_run_at: NaiveDateTime,
+    pub last_run_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NewSchedule {
+    pub command_name: String,
+    pub cron_expr: Option<String>,
+    pub interval_secs: Option<i64>,
+    pub catch_up: CatchUp,
+}
+
+/// What the daemon does with runs that were due while it wasn't running, or while the previous run was still going.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default, sqlx::Type)]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum CatchUp {
+    /// Drop missed runs and wait for the next one.
+    #[default]
+    Skip,
+    /// Run once for any number of missed runs.
+    Once,
+    /// Run every missed run, one after the other.
+    All,
+}
+
+impl CatchUp {
+    pub fn parse(name: &str) -> Result<CatchUp, LiaCoreError> {
+        match name.trim() {
+            "skip" => Ok(CatchUp::Skip),
+            "once" => Ok(CatchUp::Once),
+            "all" => Ok(CatchUp::All),
+            other => Err(LiaCoreError::InvalidInputError(format!(
+                "Unknown catch-up policy: {} (expected skip, once or all)",
+                other
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CatchUp::Skip => "skip",
+            CatchUp::Once => "once",
+            CatchUp::All => "all",
+        }
+    }
+}
+
+/// Returns the first run strictly after `after` (UTC) for a cron expression or an interval.
+pub fn next_run_after(
+    cron_expr: Option<&str>,
+    interval_secs: Option<i64>,
+    after: NaiveDateTime,
+) -> Result<NaiveDateTime, LiaCoreError> {
+    match (cron_expr, interval_secs) {
+        (Some(expr), None) => {
+            let cron = Cron::new(expr).parse().map_err(|e| {
+                LiaCoreError::InvalidInputError(format!("Invalid cron expression '{}': {}", expr, e))
+            })?;
+            let next = cron
+                .find_next_occurrence(&Local.from_utc_datetime(&after), false)
+                .map_err(|e| {
+                    LiaCoreError::InvalidInputError(format!("Cron expression '{}' never runs: {}", expr, e))
+                })?;
+            Ok(next.naive_utc())
+        }
+        (None, Some(secs)) if secs > 0 => Ok(after + chrono::Duration::seconds(secs)),
+        _ => Err(LiaCoreError::InvalidInputError(String::from(
+            "A schedule needs either a cron expression or a positive interval",
+        ))),
+    }
+}
+
+impl Schedule {
+    pub fn next_run_after(&self, after: NaiveDateTime) -> Result<NaiveDateTime, LiaCoreError> {
+        next_run_after(self.cron_expr.as_deref(), self.interval_secs, after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn test_next_run_after_interval() {
+        let next = next_run_after(None, Some(900), at("2024-05-01 10:00:00")).unwrap();
+        assert_eq!(next, at("2024-05-01 10:15:00"));
+        assert!(next_run_after(None, Some(0), at("2024-05-01 10:00:00")).is_err());
+        assert!(next_run_after(Some("* * * * *"), Some(60), at("2024-05-01 10:00:00")).is_err());
+    }
+
+    #[test]
+    fn test_next_run_after_cron() {
+        let after = at("2024-05-01 10:00:00");
+        let next = next_run_after(Some("*/5 * * * *"), None, after).unwrap();
+        assert!(next > after);
+        assert_eq!(next_run_after(Some("*/5 * * * *"), None, next).unwrap() - next, chrono::Duration::minutes(5));
+        assert!(next_run_after(Some("61 * * * *"), None, after).is_err());
+    }
+
+    #[test]
+    fn test_catch_up_parse() {
+        assert_eq!(CatchUp::parse("all").unwrap(), CatchUp::All);
+        assert_eq!(CatchUp::parse(CatchUp::Once.as_str()).unwrap(), CatchUp::Once);
+        assert!(CatchUp::parse("sometimes").is_err());
+    }
+}
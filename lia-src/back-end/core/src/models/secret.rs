@@ -0,0 +1,28 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// A stored secret without its value, for listing.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Secret {
+    pub name: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+/// A secret's value encrypted with the store's key.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EncryptedSecret {
+    pub nonce: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+    /// Authenticates the name, nonce and ciphertext.
+    pub tag: Vec<u8>,
+}
+
+/// How the store's key is derived from its passphrase.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SecretStore {
+    pub salt: Vec<u8>,
+    pub iterations: i32,
+    /// Proves a passphrase derives the same key, without storing anything that reveals it.
+    pub verifier: Vec<u8>,
+}
@@ -0,0 +1,203 @@
+use std::{
+    fs::File,
+    io::{self, Read, Write},
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    ptr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::secrets::SecretMask;
+
+/// Both ends of a freshly opened pseudo-terminal.
+pub struct Pty {
+    pub master: OwnedFd,
+    pub slave: OwnedFd,
+}
+
+impl Pty {
+    pub fn open() -> io::Result<Pty> {
+        let mut master: libc::c_int = -1;
+        let mut slave: libc::c_int = -1;
+
+        let ret = unsafe {
+            libc::openpty(&mut master, &mut slave, ptr::null_mut(), ptr::null(), ptr::null())
+        };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        unsafe {
+            Ok(Pty {
+                master: OwnedFd::from_raw_fd(master),
+                slave: OwnedFd::from_raw_fd(slave),
+            })
+        }
+    }
+}
+
+pub fn is_terminal(fd: RawFd) -> bool {
+    unsafe { libc::isatty(fd) == 1 }
+}
+
+/// Copies the window size of LiA's terminal onto the pty, so full-screen programs lay out correctly.
+pub fn copy_window_size(from: RawFd, to: RawFd) {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    unsafe {
+        if libc::ioctl(from, libc::TIOCGWINSZ, &mut size) == 0 {
+            libc::ioctl(to, libc::TIOCSWINSZ, &size);
+        }
+    }
+}
+
+/// Puts a terminal in raw mode until dropped, so keystrokes (including Ctrl-C) reach the child untouched.
+pub struct RawModeGuard {
+    fd: RawFd,
+    original: libc::termios,
+}
+
+impl RawModeGuard {
+    /// Returns `None` when `fd` is not a terminal, e.g. when LiA's stdin is a pipe.
+    pub fn enable(fd: RawFd) -> io::Result<Option<RawModeGuard>> {
+        if !is_terminal(fd) {
+            return Ok(None);
+        }
+
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        unsafe {
+            if libc::tcgetattr(fd, &mut original) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut raw = original;
+            libc::cfmakeraw(&mut raw);
+            if libc::tcsetattr(fd, libc::TCSANOW, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(Some(RawModeGuard { fd, original }))
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+/// How long output that could be the start of a secret is held back when nothing follows it, such as a prompt.
+const MASK_IDLE: Duration = Duration::from_millis(50);
+
+/// Copies everything the child writes to the pty onto LiA's stdout, with `mask` applied. Ends once every slave fd
+/// is closed.
+pub fn spawn_output_pump(master: &OwnedFd, mask: SecretMask) -> io::Result<JoinHandle<()>> {
+    let master = File::from(master.try_clone()?);
+    Ok(thread::spawn(move || copy_masked(master, io::stdout(), &mask)))
+}
+
+/// Copies `from` into `to` until either end closes, flushing after every read so interactive output shows at once.
+fn copy_masked(mut from: File, mut to: impl Write, mask: &SecretMask) {
+    let mut mask = mask.stream();
+    let mut buf = [0u8; 4096];
+    loop {
+        if mask.is_holding() && !wait_readable(from.as_raw_fd(), MASK_IDLE) {
+            // The program is waiting, e.g. on a prompt ending in what could start a secret: show it.
+            if to.write_all(&mask.finish()).and_then(|_| to.flush()).is_err() {
+                break;
+            }
+            continue;
+        }
+        let (masked, ended) = match from.read(&mut buf) {
+            Ok(0) | Err(_) => (mask.finish(), true),
+            Ok(n) => (mask.push(&buf[..n]), false),
+        };
+        if to.write_all(&masked).and_then(|_| to.flush()).is_err() || ended {
+            break;
+        }
+    }
+}
+
+/// Whether `fd` has something to read (or has closed) within `timeout`.
+fn wait_readable(fd: RawFd, timeout: Duration) -> bool {
+    let mut poll_fd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+    unsafe { libc::poll(&mut poll_fd, 1, timeout.as_millis() as libc::c_int) != 0 }
+}
+
+/// Copies LiA's stdin into the pty until `done` is set. Polls so the thread can notice `done` without input.
+pub fn spawn_input_pump(master: &OwnedFd, done: Arc<AtomicBool>) -> io::Result<JoinHandle<()>> {
+    let mut master = File::from(master.try_clone()?);
+    let stdin_fd = io::stdin().as_raw_fd();
+
+    Ok(thread::spawn(move || {
+        let mut buf = [0u8; 1024];
+        while !done.load(Ordering::Relaxed) {
+            let mut poll_fd = libc::pollfd { fd: stdin_fd, events: libc::POLLIN, revents: 0 };
+            let ready = unsafe { libc::poll(&mut poll_fd, 1, 100) };
+            if ready <= 0 {
+                continue;
+            }
+
+            // Read the fd directly: std's buffered stdin could hold bytes back from the next poll.
+            let read = unsafe { libc::read(stdin_fd, buf.as_mut_ptr().cast(), buf.len()) };
+            match usize::try_from(read) {
+                Ok(0) | Err(_) => {
+                    // Piped input is exhausted: send the terminal's EOF character so the child sees it too.
+                    let _ = master.write_all(&[4]);
+                    break;
+                }
+                Ok(n) => {
+                    if master.write_all(&buf[..n]).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_masked() {
+        let pty = Pty::open().unwrap();
+        let mut slave = File::from(pty.slave);
+        let mask = SecretMask::new(&[String::from(":hunter2"), String::from("line one\nline two")]);
+        let copied = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let pump = {
+            let master = File::from(pty.master);
+            let copied = SharedBuf(copied.clone());
+            thread::spawn(move || copy_masked(master, copied, &mask))
+        };
+
+        // A prompt ending in the start of a secret still shows while the program waits for input.
+        slave.write_all(b"Password:").unwrap();
+        thread::sleep(MASK_IDLE * 6);
+        assert_eq!(String::from_utf8_lossy(&copied.lock().unwrap()), "Password:");
+
+        slave.write_all(b"\nkey :hunter2\nline one\nline two\n:hunt").unwrap();
+        drop(slave);
+        pump.join().unwrap();
+        // The pty turns newlines into CRLF.
+        assert_eq!(String::from_utf8_lossy(&copied.lock().unwrap()), "Password:\r\nkey ****\r\n****\r\n:hunt");
+    }
+
+    struct SharedBuf(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use system::{RiskRule, SysConfigs};
+
+use crate::errors::LiaCoreError;
+
+/// A risk rule that matched a command.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RiskFinding {
+    pub rule: String,
+    pub reason: String,
+    /// The part of the command text that matched.
+    pub matched: String,
+}
+
+/// Classifies command text with a set of risk rules, by default the ones in `configs.json`.
+pub struct RiskAnalyzer {
+    rules: Vec<(RiskRule, Regex)>,
+}
+
+impl RiskAnalyzer {
+    pub fn new(rules: Vec<RiskRule>) -> Result<Self, LiaCoreError> {
+        let rules = rules
+            .into_iter()
+            .map(|rule| match Regex::new(&rule.pattern) {
+                Ok(regex) => Ok((rule, regex)),
+                Err(e) => Err(LiaCoreError::ConfigError(format!("Invalid risk rule '{}': {}", rule.name, e))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn from_configs() -> Result<Self, LiaCoreError> {
+        Self::new(SysConfigs::get_risk().rules)
+    }
+
+    /// The rules matching `text`, in the order they are configured. Empty means the command looks safe.
+    pub fn analyze(&self, text: &str) -> Vec<RiskFinding> {
+        self.rules
+            .iter()
+            .filter_map(|(rule, regex)| {
+                regex.find(text).map(|found| RiskFinding {
+                    rule: rule.name.clone(),
+                    reason: rule.reason.clone(),
+                    matched: found.as_str().trim().to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules_matching(text: &str) -> Vec<String> {
+        let analyzer = RiskAnalyzer::from_configs().unwrap();
+        analyzer.analyze(text).into_iter().map(|finding| finding.rule).collect()
+    }
+
+    #[test]
+    fn test_analyze() {
+        assert_eq!(rules_matching("sudo rm -rf /"), vec!["rm-root"]);
+        assert_eq!(rules_matching("rm -r --no-preserve-root / && echo done"), vec!["rm-root"]);
+        assert_eq!(rules_matching("dd if=image.iso of=/dev/sda bs=4M"), vec!["dd-device"]);
+        assert_eq!(rules_matching("mkfs.ext4 /dev/sdb1"), vec!["mkfs"]);
+        assert_eq!(rules_matching("chmod -R 777 /"), vec!["recursive-permissions-root"]);
+        assert_eq!(rules_matching("curl -fsSL https://example.com/install | sudo bash"), vec!["pipe-to-shell"]);
+
+        assert!(rules_matching("rm -rf ./build /tmp/cache").is_empty());
+        assert!(rules_matching("chmod -R 755 /srv/www").is_empty());
+        assert!(rules_matching("curl -s https://example.com | jq .").is_empty());
+    }
+
+    #[test]
+    fn test_invalid_rule() {
+        let rule = RiskRule { name: String::from("broken"), pattern: String::from("("), reason: String::new() };
+        assert!(matches!(RiskAnalyzer::new(vec![rule]), Err(LiaCoreError::ConfigError(_))));
+    }
+}
@@ -0,0 +1,395 @@
+use std::{
+    ffi::{CStr, CString},
+    io,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+use crate::errors::LiaCoreError;
+
+/// `struct mount_attr` from `<linux/mount.h>`, which libc doesn't define.
+#[repr(C)]
+struct MountAttr {
+    attr_set: u64,
+    attr_clr: u64,
+    propagation: u64,
+    userns_fd: u64,
+}
+
+const MOUNT_ATTR_RDONLY: u64 = 0x1;
+const OPEN_TREE_CLONE: libc::c_uint = 0x1;
+const MOVE_MOUNT_F_EMPTY_PATH: libc::c_uint = 0x4;
+
+/// Covered with an empty tmpfs: they hold the sockets of the user's session (systemd, D-Bus, ssh-agent), which
+/// stay connectable through a read-only mount.
+const HIDDEN_DIRS: [&str; 4] = ["/tmp", "/var/tmp", "/run/user", "/run/dbus"];
+
+/// Sockets outside those directories, covered with `/dev/null`.
+const BLOCKED_SOCKETS: [&str; 3] = ["/run/docker.sock", "/run/podman/podman.sock", "/run/containerd/containerd.sock"];
+
+/// A change to the sandbox's filesystem. Applied parents first, so a deeper one lands on top.
+#[derive(Debug, Clone)]
+enum Step {
+    Hide(CString),
+    Block(CString),
+    /// Puts back a directory as it was before anything was hidden. `parents` are created first, in case it was
+    /// under a hidden directory.
+    Expose { path: CString, parents: Vec<CString>, tree: usize, writable: bool },
+}
+
+/// Namespaces and mounts for a sandboxed run, prepared before the fork so the child only makes system calls.
+#[derive(Debug, Clone)]
+pub struct SandboxSetup {
+    uid_map: Vec<u8>,
+    gid_map: Vec<u8>,
+    steps: Vec<Step>,
+    /// Exposed directories, cloned before anything is hidden. Sized up front, since the child can't allocate.
+    trees: Vec<libc::c_int>,
+    cwd: CString,
+}
+
+impl SandboxSetup {
+    /// Keeps the command's `uid` and `gid` inside the namespace, so it owns the same files it would outside.
+    /// Root is refused: with all capabilities in the namespace it could make the filesystem writable again.
+    pub fn new(writable: &[PathBuf], cwd: &Path, uid: u32, gid: u32) -> Result<Self, LiaCoreError> {
+        if uid == 0 {
+            return Err(LiaCoreError::InvalidInputError(String::from(
+                "Sandboxed commands can't run as root; run LiA as a regular user or through sudo",
+            )));
+        }
+
+        let mut hidden: Vec<PathBuf> = HIDDEN_DIRS
+            .iter()
+            .map(PathBuf::from)
+            .chain(std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from))
+            .filter_map(|dir| dir.canonicalize().ok().filter(|dir| dir.is_dir()))
+            .collect();
+        hidden.sort();
+        hidden.dedup();
+        let nested: Vec<PathBuf> =
+            hidden.iter().filter(|dir| hidden.iter().any(|other| other != *dir && dir.starts_with(other))).cloned().collect();
+        hidden.retain(|dir| !nested.contains(dir));
+
+        let mut blocked: Vec<PathBuf> = BLOCKED_SOCKETS
+            .iter()
+            .map(PathBuf::from)
+            .chain(std::env::var_os("SSH_AUTH_SOCK").map(PathBuf::from))
+            .filter_map(|socket| socket.canonicalize().ok().filter(|socket| !socket.is_dir()))
+            .collect();
+        blocked.sort();
+        blocked.dedup();
+
+        let cwd = cwd.canonicalize().unwrap_or_else(|_| cwd.to_path_buf());
+        let mut exposed: Vec<(PathBuf, bool)> = writable.iter().map(|dir| (dir.clone(), true)).collect();
+        // Keep the working directory readable, unless it's hidden on purpose.
+        if hidden.iter().any(|dir| cwd.starts_with(dir) && cwd != *dir) && !writable.iter().any(|dir| cwd.starts_with(dir)) {
+            exposed.push((cwd.clone(), false));
+        }
+
+        let mut ordered: Vec<(&Path, Step)> = Vec::new();
+        for dir in &hidden {
+            ordered.push((dir, Step::Hide(c_path(dir)?)));
+        }
+        for socket in &blocked {
+            ordered.push((socket, Step::Block(c_path(socket)?)));
+        }
+        for (tree, (dir, writable)) in exposed.iter().enumerate() {
+            let parents = dir.ancestors().skip(1).collect::<Vec<_>>().into_iter().rev().map(c_path).collect::<Result<_, _>>()?;
+            ordered.push((dir, Step::Expose { path: c_path(dir)?, parents, tree, writable: *writable }));
+        }
+        ordered.sort_by_key(|(path, _)| path.components().count());
+
+        Ok(SandboxSetup {
+            uid_map: format!("{} {} 1", uid, uid).into_bytes(),
+            gid_map: format!("{} {} 1", gid, gid).into_bytes(),
+            steps: ordered.into_iter().map(|(_, step)| step).collect(),
+            trees: vec![-1; exposed.len()],
+            cwd: c_path(&cwd)?,
+        })
+    }
+
+    /// Moves the calling process into new user, mount, network and PID namespaces and sets up its filesystem.
+    /// A PID namespace only applies to children, so this forks twice: the first child is the namespace's init,
+    /// which reaps orphans, and its child returns to run the command. Init only gets signals it handles, which is
+    /// why the command isn't init itself. The calling process stays outside, waiting to exit like the command.
+    pub fn enter(&mut self) -> io::Result<()> {
+        // Switching users makes a process undumpable, which leaves its /proc files owned by root.
+        check(unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 1) })?;
+        check(unsafe {
+            libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWNET | libc::CLONE_NEWPID)
+        })?;
+        // Unprivileged processes must give up setgroups before they may map their group.
+        write_file(c"/proc/self/setgroups", b"deny")?;
+        write_file(c"/proc/self/uid_map", &self.uid_map)?;
+        write_file(c"/proc/self/gid_map", &self.gid_map)?;
+        // Keep our mounts from propagating back to the host.
+        check(unsafe {
+            libc::mount(std::ptr::null(), c"/".as_ptr(), std::ptr::null(), libc::MS_REC | libc::MS_PRIVATE, std::ptr::null())
+        })?;
+
+        let mut status_pipe = [0; 2];
+        check(unsafe { libc::pipe2(status_pipe.as_mut_ptr(), libc::O_CLOEXEC) })?;
+        let [status_read, status_write] = status_pipe;
+        let init = unsafe { libc::fork() };
+        check(init)?;
+        if init > 0 {
+            unsafe { libc::close(status_write) };
+            wait_for_init(init, status_read);
+        }
+        let command = unsafe { libc::fork() };
+        check(command)?;
+        if command > 0 {
+            unsafe { libc::close(status_read) };
+            run_init(command, status_write);
+        }
+        unsafe {
+            libc::close(status_read);
+            libc::close(status_write);
+        }
+
+        self.mount_filesystem()?;
+        check(unsafe { libc::chdir(self.cwd.as_ptr()) })
+    }
+
+    /// Makes every mount read-only, mounts a /proc for the new PID namespace, hides the session's sockets and
+    /// puts back the exposed directories.
+    fn mount_filesystem(&mut self) -> io::Result<()> {
+        for step in &self.steps {
+            if let Step::Expose { path, tree, .. } = step {
+                let flags = OPEN_TREE_CLONE | libc::O_CLOEXEC as libc::c_uint | libc::AT_RECURSIVE as libc::c_uint;
+                let fd = unsafe { libc::syscall(libc::SYS_open_tree, libc::AT_FDCWD, path.as_ptr(), flags) };
+                check(fd as libc::c_int)?;
+                self.trees[*tree] = fd as libc::c_int;
+            }
+        }
+
+        set_mount_attr(libc::AT_FDCWD, c"/", 0, MOUNT_ATTR_RDONLY, 0)?;
+        check(unsafe {
+            libc::mount(
+                c"proc".as_ptr(),
+                c"/proc".as_ptr(),
+                c"proc".as_ptr(),
+                libc::MS_NOSUID | libc::MS_NODEV | libc::MS_NOEXEC,
+                std::ptr::null(),
+            )
+        })?;
+
+        for step in &self.steps {
+            match step {
+                Step::Hide(dir) => check(unsafe {
+                    libc::mount(c"tmpfs".as_ptr(), dir.as_ptr(), c"tmpfs".as_ptr(), libc::MS_NOSUID | libc::MS_NODEV, std::ptr::null())
+                })?,
+                Step::Block(socket) => check(unsafe {
+                    libc::mount(c"/dev/null".as_ptr(), socket.as_ptr(), std::ptr::null(), libc::MS_BIND, std::ptr::null())
+                })
+                .or_else(|e| if e.raw_os_error() == Some(libc::ENOENT) { Ok(()) } else { Err(e) })?,
+                Step::Expose { path, parents, tree, writable } => {
+                    for parent in parents {
+                        // Fails harmlessly for directories that exist.
+                        unsafe { libc::mkdir(parent.as_ptr(), 0o755) };
+                    }
+                    unsafe { libc::mkdir(path.as_ptr(), 0o755) };
+                    let fd = self.trees[*tree];
+                    if !writable {
+                        set_mount_attr(fd, c"", libc::AT_EMPTY_PATH as libc::c_uint, MOUNT_ATTR_RDONLY, 0)?;
+                    }
+                    let moved = unsafe {
+                        libc::syscall(libc::SYS_move_mount, fd, c"".as_ptr(), libc::AT_FDCWD, path.as_ptr(), MOVE_MOUNT_F_EMPTY_PATH)
+                    };
+                    check(moved as libc::c_int)?;
+                    unsafe { libc::close(fd) };
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The process that started the sandbox: waits for init to report how the command ended, then ends the same way.
+/// It ignores the signals meant for the command, which reach it too through the process group, so it lasts
+/// exactly as long as the command.
+fn wait_for_init(init: libc::pid_t, status_read: libc::c_int) -> ! {
+    unsafe {
+        for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP, libc::SIGQUIT] {
+            libc::signal(signal, libc::SIG_IGN);
+        }
+        close_fds_except(status_read);
+
+        let mut status: libc::c_int = 0;
+        let read = libc::read(status_read, (&mut status as *mut libc::c_int).cast(), std::mem::size_of::<libc::c_int>());
+        if read != std::mem::size_of::<libc::c_int>() as isize {
+            // Init was killed before the command ended.
+            while libc::waitpid(init, &mut status, 0) < 0 && io::Error::last_os_error().raw_os_error() == Some(libc::EINTR) {}
+        }
+        exit_like(status)
+    }
+}
+
+/// Init of the sandbox's PID namespace: reaps whatever is left to it and reports the command's wait status once it
+/// ends. Exiting then ends every other process in the namespace.
+fn run_init(command: libc::pid_t, status_write: libc::c_int) -> ! {
+    unsafe {
+        close_fds_except(status_write);
+        loop {
+            let mut status: libc::c_int = 0;
+            let pid = libc::waitpid(-1, &mut status, 0);
+            if pid == command {
+                libc::write(status_write, (&status as *const libc::c_int).cast(), std::mem::size_of::<libc::c_int>());
+                libc::_exit(0);
+            }
+            if pid < 0 && io::Error::last_os_error().raw_os_error() != Some(libc::EINTR) {
+                libc::_exit(1);
+            }
+        }
+    }
+}
+
+/// Exits with the same code, or is killed by the same signal, as the process whose wait status is `status`.
+fn exit_like(status: libc::c_int) -> ! {
+    unsafe {
+        if libc::WIFSIGNALED(status) {
+            let signal = libc::WTERMSIG(status);
+            let no_core = libc::rlimit { rlim_cur: 0, rlim_max: 0 };
+            libc::setrlimit(libc::RLIMIT_CORE, &no_core);
+            libc::signal(signal, libc::SIG_DFL);
+            libc::kill(libc::getpid(), signal);
+            libc::_exit(128 + signal);
+        }
+        libc::_exit(if libc::WIFEXITED(status) { libc::WEXITSTATUS(status) } else { 1 })
+    }
+}
+
+/// Closes every fd but the standard ones and `keep`, so processes that never exec don't hold pipes open, such as
+/// the one through which the parent learns that the command started.
+fn close_fds_except(keep: libc::c_int) {
+    let keep = keep as libc::c_uint;
+    unsafe {
+        if keep > 3 {
+            libc::syscall(libc::SYS_close_range, 3, keep - 1, 0);
+        }
+        libc::syscall(libc::SYS_close_range, keep + 1, libc::c_uint::MAX, 0);
+    }
+}
+
+fn c_path(path: &Path) -> Result<CString, LiaCoreError> {
+    CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| LiaCoreError::InvalidInputError(format!("Invalid path: {}", path.display())))
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Sets and clears mount attributes on `path` relative to `dir_fd`, and every mount below it.
+fn set_mount_attr(dir_fd: libc::c_int, path: &CStr, flags: libc::c_uint, set: u64, clear: u64) -> io::Result<()> {
+    let attr = MountAttr { attr_set: set, attr_clr: clear, propagation: 0, userns_fd: 0 };
+    let result = unsafe {
+        libc::syscall(
+            libc::SYS_mount_setattr,
+            dir_fd,
+            path.as_ptr(),
+            flags | libc::AT_RECURSIVE as libc::c_uint,
+            &attr as *const MountAttr,
+            std::mem::size_of::<MountAttr>(),
+        )
+    };
+    check(result as libc::c_int)
+}
+
+fn write_file(path: &CStr, content: &[u8]) -> io::Result<()> {
+    let fd = unsafe { libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC) };
+    check(fd)?;
+    let written = unsafe { libc::write(fd, content.as_ptr().cast(), content.len()) };
+    let result = if written < 0 { Err(io::Error::last_os_error()) } else { Ok(()) };
+    unsafe { libc::close(fd) };
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        net::TcpListener,
+        os::unix::{fs::PermissionsExt, net::UnixListener, process::CommandExt},
+        process::{Command, Output},
+    };
+
+    use super::*;
+
+    /// Root is refused, so tests running as root sandbox `nobody` instead.
+    fn sandbox_ids() -> (u32, u32) {
+        match unsafe { libc::getuid() } {
+            0 => (65534, 65534),
+            uid => (uid, unsafe { libc::getgid() }),
+        }
+    }
+
+    fn run_sandboxed(script: &str, writable: &[PathBuf], cwd: &Path) -> Output {
+        let (uid, gid) = sandbox_ids();
+        let mut setup = SandboxSetup::new(writable, cwd, uid, gid).unwrap();
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(script);
+        unsafe {
+            command.pre_exec(move || {
+                if libc::getuid() == 0
+                    && (libc::setgroups(0, std::ptr::null()) < 0 || libc::setgid(gid) < 0 || libc::setuid(uid) < 0)
+                {
+                    return Err(io::Error::last_os_error());
+                }
+                setup.enter()
+            });
+        }
+        command.output().unwrap()
+    }
+
+    /// A directory anyone may write to, so the sandboxed user can, whether it is us or `nobody`.
+    fn shared_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lia-sandbox-{}-{}", name, std::process::id()));
+        fs::create_dir_all(dir.join("writable")).unwrap();
+        for dir in [dir.clone(), dir.join("writable")] {
+            fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).unwrap();
+        }
+        dir.canonicalize().unwrap()
+    }
+
+    #[test]
+    fn test_writes() {
+        let dir = shared_dir("writes");
+        let output = run_sandboxed(
+            "touch blocked; touch writable/allowed; echo $$",
+            &[dir.join("writable")],
+            &dir,
+        );
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "2", "{:?}", output);
+        assert!(!dir.join("blocked").exists());
+        assert!(dir.join("writable/allowed").exists());
+
+        let output = run_sandboxed("touch /var/lia-sandbox-test", &[], &dir);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Read-only file system"), "{:?}", output);
+        fs::remove_dir_all(dir).unwrap();
+        assert!(SandboxSetup::new(&[], Path::new("/"), 0, 0).is_err());
+    }
+
+    #[test]
+    fn test_network_and_sockets() {
+        let dir = shared_dir("network");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let _socket = UnixListener::bind(dir.join("writable/agent.sock")).unwrap();
+
+        let script = format!(
+            "tail -n +3 /proc/net/dev | cut -d: -f1 | tr -d ' '; test -S {0}/agent.sock || echo hidden; \
+             test -S {1}/agent.sock && echo exposed; bash -c 'echo > /dev/tcp/127.0.0.1/{2}' 2>/dev/null && echo connected",
+            dir.display(),
+            dir.join("writable").display(),
+            port
+        );
+        let output = run_sandboxed(&script, &[dir.join("writable")], Path::new("/"));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "lo\nhidden\nexposed\n", "{:?}", output);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
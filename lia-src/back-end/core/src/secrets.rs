@@ -0,0 +1,279 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use chacha20poly1305::{
+    aead::{AeadCore, AeadInPlace, KeyInit, OsRng},
+    Tag, XChaCha20Poly1305, XNonce,
+};
+use hmac::{Hmac, Mac};
+use pbkdf2::pbkdf2_hmac_array;
+use rand::RngCore;
+use regex::{Captures, Regex};
+use sha2::Sha256;
+
+use crate::{errors::LiaCoreError, models::secret::EncryptedSecret};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Environment variable LiA reads the secret store's passphrase from when there is no terminal to ask on, e.g.
+/// for `lia daemon`. It is never passed on to commands.
+pub const PASSPHRASE_ENV: &str = "LIA_SECRET_PASSPHRASE";
+
+/// PBKDF2 rounds for new stores; existing stores keep the count they were created with.
+pub const KDF_ITERATIONS: u32 = 600_000;
+
+/// Shown in place of secret values in a command's output.
+pub const MASK: &str = "****";
+
+fn placeholder_regex() -> &'static Regex {
+    static PLACEHOLDER: OnceLock<Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| Regex::new(r"\{\{\s*secret:([A-Za-z0-9_.-]+)\s*\}\}").unwrap())
+}
+
+pub fn validate_name(name: &str) -> Result<(), LiaCoreError> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-')) {
+        return Err(LiaCoreError::InvalidInputError(format!(
+            "Invalid secret name: {} (use letters, digits, '_', '.' and '-')",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// Names of the secrets referenced as `{{secret:name}}` in `text`.
+pub fn placeholders(text: &str) -> Vec<String> {
+    placeholder_regex().captures_iter(text).map(|captures| captures[1].to_string()).collect()
+}
+
+/// `text` with each `{{secret:name}}` replaced by the secret's value. Unknown names are left as they are.
+pub fn substitute(text: &str, values: &HashMap<String, String>) -> String {
+    placeholder_regex()
+        .replace_all(text, |captures: &Captures| match values.get(&captures[1]) {
+            Some(value) => value.clone(),
+            None => captures[0].to_string(),
+        })
+        .into_owned()
+}
+
+/// Replaces secret values with `MASK` in text a command printed.
+#[derive(Debug, Clone, Default)]
+pub struct SecretMask {
+    /// Longest first, so a secret containing another is masked whole.
+    values: Vec<Vec<u8>>,
+}
+
+impl SecretMask {
+    /// Multi-line values are matched with CRLF line endings too, which is how they come out of a pty.
+    pub fn new<'a>(values: impl IntoIterator<Item = &'a String>) -> Self {
+        let mut values: Vec<Vec<u8>> = values
+            .into_iter()
+            .filter(|value| !value.is_empty())
+            .flat_map(|value| {
+                let crlf = value.contains('\n').then(|| value.replace("\r\n", "\n").replace('\n', "\r\n").into_bytes());
+                std::iter::once(value.as_bytes().to_vec()).chain(crlf)
+            })
+            .collect();
+        values.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        values.dedup();
+        SecretMask { values }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut stream = self.stream();
+        let mut masked = stream.push(text.as_bytes());
+        masked.extend(stream.finish());
+        String::from_utf8_lossy(&masked).into_owned()
+    }
+
+    /// Masks output that arrives in pieces, such as reads from a pipe or a pty.
+    pub fn stream(&self) -> MaskStream {
+        MaskStream { values: self.values.clone(), pending: Vec::new() }
+    }
+}
+
+/// A `SecretMask` over a stream of output. Secrets split across reads or lines are still masked, because
+/// output that could be the start of one is held back until the rest arrives.
+pub struct MaskStream {
+    values: Vec<Vec<u8>>,
+    pending: Vec<u8>,
+}
+
+impl MaskStream {
+    /// The masked output that is ready to show.
+    pub fn push(&mut self, data: &[u8]) -> Vec<u8> {
+        if self.values.is_empty() {
+            return data.to_vec();
+        }
+        self.pending.extend_from_slice(data);
+        self.drain(false)
+    }
+
+    /// Whatever was held back, once the output ended or paused. A secret split across the pause isn't masked.
+    pub fn finish(&mut self) -> Vec<u8> {
+        self.drain(true)
+    }
+
+    /// Whether output is being held back until more arrives.
+    pub fn is_holding(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    fn drain(&mut self, end: bool) -> Vec<u8> {
+        let mut masked = Vec::new();
+        let mut i = 0;
+        'scan: while i < self.pending.len() {
+            let rest = &self.pending[i..];
+            for value in &self.values {
+                if rest.starts_with(value) {
+                    masked.extend_from_slice(MASK.as_bytes());
+                    i += value.len();
+                    continue 'scan;
+                }
+                if !end && value.starts_with(rest) {
+                    break 'scan;
+                }
+            }
+            masked.push(rest[0]);
+            i += 1;
+        }
+        self.pending.drain(..i);
+        masked
+    }
+}
+
+/// Key derived from the store's passphrase: one half encrypts, the other proves the passphrase.
+pub struct SecretKey {
+    cipher_key: [u8; 32],
+    verifier_key: [u8; 32],
+}
+
+impl SecretKey {
+    /// PBKDF2-HMAC-SHA256 of the passphrase, split into separate keys with HMAC.
+    pub fn derive(passphrase: &str, salt: &[u8], iterations: u32) -> SecretKey {
+        let master = pbkdf2_hmac_array::<Sha256, 32>(passphrase.as_bytes(), salt, iterations);
+        SecretKey {
+            cipher_key: hmac(&master).chain_update(b"lia-secrets-cipher").finalize().into_bytes().into(),
+            verifier_key: hmac(&master).chain_update(b"lia-secrets-verifier").finalize().into_bytes().into(),
+        }
+    }
+
+    /// Stored with the store to tell a wrong passphrase apart from corrupted secrets.
+    pub fn verifier(&self) -> Vec<u8> {
+        self.verifier_mac().finalize().into_bytes().to_vec()
+    }
+
+    pub fn matches(&self, verifier: &[u8]) -> bool {
+        self.verifier_mac().verify_slice(verifier).is_ok()
+    }
+
+    /// Encrypts with XChaCha20-Poly1305, authenticating the name too so a value can't be swapped onto another
+    /// secret's name.
+    pub fn encrypt(&self, name: &str, value: &str) -> EncryptedSecret {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let mut ciphertext = value.as_bytes().to_vec();
+        let tag = self
+            .cipher()
+            .encrypt_in_place_detached(&nonce, name.as_bytes(), &mut ciphertext)
+            .expect("secret values fit in a single message");
+        EncryptedSecret { nonce: nonce.to_vec(), ciphertext, tag: tag.to_vec() }
+    }
+
+    pub fn decrypt(&self, name: &str, secret: &EncryptedSecret) -> Result<String, LiaCoreError> {
+        let corrupted = || LiaCoreError::SecretError(format!("Secret {} is corrupted or was tampered with", name));
+        if secret.nonce.len() != 24 || secret.tag.len() != 16 {
+            return Err(corrupted());
+        }
+        let mut plaintext = secret.ciphertext.clone();
+        self.cipher()
+            .decrypt_in_place_detached(
+                XNonce::from_slice(&secret.nonce),
+                name.as_bytes(),
+                &mut plaintext,
+                Tag::from_slice(&secret.tag),
+            )
+            .map_err(|_| corrupted())?;
+
+        String::from_utf8(plaintext).map_err(|_| LiaCoreError::SecretError(format!("Secret {} is not valid UTF-8", name)))
+    }
+
+    fn verifier_mac(&self) -> HmacSha256 {
+        hmac(&self.verifier_key).chain_update(b"lia-secrets-verifier")
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(&self.cipher_key.into())
+    }
+}
+
+pub fn random_salt() -> Vec<u8> {
+    let mut salt = vec![0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    salt
+}
+
+fn hmac(key: &[u8]) -> HmacSha256 {
+    <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC takes keys of any size")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_derive() {
+        // RFC 7914, section 11: PBKDF2-HMAC-SHA256 of "passwd" with salt "salt" and one round.
+        let master = pbkdf2_hmac_array::<Sha256, 8>(b"passwd", b"salt", 1);
+        assert_eq!(master, [0x55, 0xac, 0x04, 0x6e, 0x56, 0xe3, 0x08, 0x9f]);
+
+        let key = SecretKey::derive("passwd", b"salt", 1);
+        assert_ne!(key.cipher_key, key.verifier_key);
+        assert!(!key.matches(&key.verifier()[..16]));
+    }
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let key = SecretKey::derive("correct horse", b"0123456789abcdef", 10);
+        let secret = key.encrypt("token", "s3cr3t value that spans more than one block of key stream");
+        assert_ne!(secret.ciphertext, b"s3cr3t value that spans more than one block of key stream");
+        assert_eq!(key.decrypt("token", &secret).unwrap(), "s3cr3t value that spans more than one block of key stream");
+        assert!(key.decrypt("other", &secret).is_err());
+        assert!(key.matches(&key.verifier()));
+
+        let wrong = SecretKey::derive("wrong horse", b"0123456789abcdef", 10);
+        assert!(!wrong.matches(&key.verifier()));
+        assert!(wrong.decrypt("token", &secret).is_err());
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let text = "curl -H 'Authorization: Bearer {{secret:github_token}}' {{ secret:host }} {{secret:missing}}";
+        assert_eq!(placeholders(text), vec!["github_token", "host", "missing"]);
+
+        let values = HashMap::from([
+            (String::from("github_token"), String::from("ghp_123")),
+            (String::from("host"), String::from("api.github.com")),
+        ]);
+        assert_eq!(
+            substitute(text, &values),
+            "curl -H 'Authorization: Bearer ghp_123' api.github.com {{secret:missing}}"
+        );
+
+        let mask = SecretMask::new(values.values());
+        assert_eq!(mask.apply("token ghp_123 for api.github.com"), "token **** for ****");
+    }
+
+    #[test]
+    fn test_mask_stream() {
+        let values = [String::from("-----BEGIN KEY-----\nabc\n-----END KEY-----"), String::from("ghp_123")];
+        let mask = SecretMask::new(&values);
+        assert_eq!(mask.apply("key: -----BEGIN KEY-----\nabc\n-----END KEY-----\n"), "key: ****\n");
+
+        let mut stream = mask.stream();
+        let mut output = Vec::new();
+        for chunk in ["token gh", "p_1", "23\n-----BEGIN KEY-----\n", "abc\n-----END KEY-----\ngh", "p_12"] {
+            output.extend(stream.push(chunk.as_bytes()));
+        }
+        assert_eq!(String::from_utf8_lossy(&output), "token ****\n****\n");
+        output.extend(stream.finish());
+        assert_eq!(String::from_utf8_lossy(&output), "token ****\n****\nghp_12");
+    }
+}
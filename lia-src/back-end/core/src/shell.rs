@@ -0,0 +1,135 @@
+use crate::errors::LiaCoreError;
+
+/// Splits a shell string into argv tokens, honouring quotes and backslash escapes like `sh` would.
+pub fn split(command_text: &str) -> Result<Vec<String>, LiaCoreError> {
+    let argv = shell_words::split(command_text).map_err(|e| {
+        LiaCoreError::InvalidInputError(format!("Could not tokenize command: {}", e))
+    })?;
+
+    if argv.is_empty() {
+        return Err(LiaCoreError::InvalidInputError(String::from("Command is empty")));
+    }
+    Ok(argv)
+}
+
+/// Joins argv tokens back into a string that `split` turns into the same tokens.
+pub fn join(argv: &[String]) -> String {
+    shell_words::join(argv)
+}
+
+/// Lists the shell features used by `command_text` that would be lost when running it without a shell.
+pub fn shell_features(command_text: &str) -> Vec<&'static str> {
+    let mut features = Vec::new();
+    let mut add = |feature: &'static str| {
+        if !features.contains(&feature) {
+            features.push(feature);
+        }
+    };
+
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut at_word_start = true;
+    let mut substitution_depth = 0;
+    let mut chars = command_text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_single {
+            in_single = c != '\'';
+            continue;
+        }
+
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\'' if !in_double => in_single = true,
+            '"' => in_double = !in_double,
+            '$' if chars.peek() == Some(&'(') => {
+                chars.next();
+                substitution_depth += 1;
+                add("command substitution");
+            }
+            '`' => add("command substitution"),
+            '$' => add("variable expansion"),
+            ')' if substitution_depth > 0 => substitution_depth -= 1,
+            _ if in_double => {}
+            '|' => add("pipe"),
+            '&' | ';' => add("command list"),
+            '<' | '>' => add("redirection"),
+            '(' | ')' => add("subshell"),
+            '*' | '?' | '[' => add("glob"),
+            '~' if at_word_start => add("home expansion"),
+            _ => {}
+        }
+
+        at_word_start = !in_double && c.is_whitespace();
+    }
+
+    features
+}
+
+/// Whether `command_text` reads positional parameters (`$1`, `${2}`, `$@`, `$*`, `$#`) outside single quotes.
+pub fn uses_positional_params(command_text: &str) -> bool {
+    let mut in_single = false;
+    let mut in_double = false;
+    let mut chars = command_text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_single {
+            in_single = c != '\'';
+            continue;
+        }
+
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '\'' if !in_double => in_single = true,
+            '"' => in_double = !in_double,
+            '$' => {
+                if chars.peek() == Some(&'{') {
+                    chars.next();
+                }
+                if matches!(chars.peek(), Some('1'..='9' | '@' | '*' | '#')) {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_join() {
+        let argv = split(r#"grep -r "hello world" 'it''s' a\ b"#).unwrap();
+        assert_eq!(argv, vec!["grep", "-r", "hello world", "its", "a b"]);
+        assert_eq!(split(&join(&argv)).unwrap(), argv);
+        assert!(split("echo 'unterminated").is_err());
+        assert!(split("   ").is_err());
+    }
+
+    #[test]
+    fn test_shell_features() {
+        assert!(shell_features("ls -la /tmp").is_empty());
+        assert!(shell_features("echo 'a | b > c'").is_empty());
+        assert_eq!(shell_features("ps aux | grep x > out.txt"), vec!["pipe", "redirection"]);
+        assert_eq!(shell_features("echo \"$HOME\""), vec!["variable expansion"]);
+        assert_eq!(shell_features("echo $(date) && ls *.rs"), vec!["command substitution", "command list", "glob"]);
+        assert_eq!(shell_features("cd ~/code"), vec!["home expansion"]);
+        assert!(shell_features("git log --format=%h~1").is_empty());
+    }
+
+    #[test]
+    fn test_uses_positional_params() {
+        assert!(uses_positional_params("grep \"$1\" /var/log/syslog"));
+        assert!(uses_positional_params("journalctl \"$@\""));
+        assert!(uses_positional_params("echo ${2:-default}"));
+        assert!(!uses_positional_params("echo '$1' $HOME $0 \\$@"));
+    }
+}
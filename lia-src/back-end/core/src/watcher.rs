@@ -0,0 +1,187 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver},
+    time::Instant,
+};
+
+use crate::errors::LiaCoreError;
+
+/// Directories that version control and common build tools write to, ignored wherever they are.
+const IGNORED_DIRS: [&str; 6] = [".git", ".hg", ".svn", "target", "node_modules", "__pycache__"];
+
+/// Watches directories recursively and reports changes to files matching a set of globs.
+pub struct FileWatcher {
+    // Stops watching when dropped.
+    _watcher: RecommendedWatcher,
+    events: UnboundedReceiver<PathBuf>,
+    roots: Vec<PathBuf>,
+    globs: Option<GlobSet>,
+    ignore: Option<GlobSet>,
+    debounce: Duration,
+    /// Changes seen so far and when they have settled, kept here so `changed` can be cancelled and resumed.
+    pending: Vec<PathBuf>,
+    settled_at: Option<Instant>,
+}
+
+impl FileWatcher {
+    /// `globs` and `ignore` are matched against paths relative to the watched directory and against file names;
+    /// with no globs every file counts.
+    pub fn new(paths: &[PathBuf], globs: &[String], ignore: &[String], debounce: Duration) -> Result<Self, LiaCoreError> {
+        let roots = paths
+            .iter()
+            .map(|path| {
+                std::fs::canonicalize(path).map_err(|e| {
+                    LiaCoreError::InvalidInputError(format!("Cannot watch {}: {}", path.display(), e))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (tx, events) = unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                if !matches!(event.kind, EventKind::Access(_)) {
+                    for path in event.paths {
+                        let _ = tx.send(path);
+                    }
+                }
+            }
+        })
+        .map_err(|e| LiaCoreError::WatchError(e.to_string()))?;
+
+        for root in &roots {
+            watcher
+                .watch(root, RecursiveMode::Recursive)
+                .map_err(|e| LiaCoreError::WatchError(format!("{}: {}", root.display(), e)))?;
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+            roots,
+            globs: build_globs(globs)?,
+            ignore: build_globs(ignore)?,
+            debounce,
+            pending: Vec::new(),
+            settled_at: None,
+        })
+    }
+
+    /// Waits for a matching change, then until no further change happens for the debounce period.
+    /// Returns the changed files, sorted and without duplicates. Cancel-safe: changes seen by a call that is
+    /// dropped are returned by the next one.
+    pub async fn changed(&mut self) -> Option<Vec<PathBuf>> {
+        loop {
+            let event = match self.settled_at {
+                None => self.events.recv().await,
+                Some(settled_at) => match tokio::time::timeout_at(settled_at, self.events.recv()).await {
+                    Ok(event) => event,
+                    Err(_) => return Some(self.take_pending()),
+                },
+            };
+            match event {
+                Some(path) => {
+                    if is_relevant(&path, &self.roots, self.globs.as_ref(), self.ignore.as_ref()) {
+                        self.pending.push(path);
+                        self.settled_at = Some(Instant::now() + self.debounce);
+                    }
+                }
+                None if self.pending.is_empty() => return None,
+                None => return Some(self.take_pending()),
+            }
+        }
+    }
+
+    fn take_pending(&mut self) -> Vec<PathBuf> {
+        self.settled_at = None;
+        let mut changed = std::mem::take(&mut self.pending);
+        changed.sort();
+        changed.dedup();
+        changed
+    }
+}
+
+fn build_globs(globs: &[String]) -> Result<Option<GlobSet>, LiaCoreError> {
+    if globs.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        let glob = Glob::new(glob)
+            .map_err(|e| LiaCoreError::InvalidInputError(format!("Invalid glob '{}': {}", glob, e)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| LiaCoreError::InvalidInputError(format!("Invalid globs: {}", e)))
+}
+
+/// Changes inside `IGNORED_DIRS` or matching `ignore` don't count, so commits, checkouts and the command's own
+/// build output don't trigger on their own.
+fn is_relevant(path: &Path, roots: &[PathBuf], globs: Option<&GlobSet>, ignore: Option<&GlobSet>) -> bool {
+    let relative = roots
+        .iter()
+        .find_map(|root| path.strip_prefix(root).ok())
+        .unwrap_or(path);
+
+    if relative.components().any(|component| IGNORED_DIRS.iter().any(|dir| component.as_os_str() == *dir)) {
+        return false;
+    }
+    let matches = |globs: &GlobSet| globs.is_match(relative) || path.file_name().is_some_and(|name| globs.is_match(name));
+    if ignore.is_some_and(matches) {
+        return false;
+    }
+
+    match globs {
+        None => true,
+        Some(globs) => matches(globs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_relevant() {
+        let roots = vec![PathBuf::from("/repo")];
+        let globs = build_globs(&[String::from("*.rs"), String::from("assets/**")]).unwrap();
+
+        assert!(is_relevant(Path::new("/repo/src/main.rs"), &roots, globs.as_ref(), None));
+        assert!(is_relevant(Path::new("/repo/assets/img/logo.png"), &roots, globs.as_ref(), None));
+        assert!(!is_relevant(Path::new("/repo/README.md"), &roots, globs.as_ref(), None));
+        assert!(!is_relevant(Path::new("/repo/.git/index"), &roots, None, None));
+        assert!(!is_relevant(Path::new("/repo/target/debug/build.rs"), &roots, globs.as_ref(), None));
+        assert!(is_relevant(Path::new("/repo/README.md"), &roots, None, None));
+        assert!(build_globs(&[String::from("src/[")]).is_err());
+
+        let ignore = build_globs(&[String::from("*.log"), String::from("out/**")]).unwrap();
+        assert!(!is_relevant(Path::new("/repo/logs/run.log"), &roots, None, ignore.as_ref()));
+        assert!(!is_relevant(Path::new("/repo/out/report.html"), &roots, None, ignore.as_ref()));
+        assert!(is_relevant(Path::new("/repo/src/main.rs"), &roots, None, ignore.as_ref()));
+    }
+
+    #[tokio::test]
+    async fn test_changed_is_cancel_safe() {
+        let dir = std::env::temp_dir().join(format!("lia-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut watcher = FileWatcher::new(std::slice::from_ref(&dir), &[], &[], Duration::from_millis(300)).unwrap();
+
+        std::fs::write(dir.join("a.txt"), "a").unwrap();
+        // Gives up mid-debounce, like `lia watch` does whenever the running command prints a line.
+        assert!(tokio::time::timeout(Duration::from_millis(100), watcher.changed()).await.is_err());
+        std::fs::write(dir.join("b.txt"), "b").unwrap();
+
+        let changed = watcher.changed().await.unwrap();
+        let root = dir.canonicalize().unwrap();
+        assert_eq!(changed, vec![root.join("a.txt"), root.join("b.txt")]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,15 @@
+-- Commands started with `lia run --detach`. Keyed by a small integer so ids are easy to type.
+-- The command is referenced by name rather than id so the job history survives deleting the command.
+CREATE TABLE jobs (
+    id SERIAL PRIMARY KEY,
+    command_name VARCHAR NOT NULL,
+    status TEXT NOT NULL,
+    -- Pid of the supervisor process that runs the command and records how it ended.
+    pid INTEGER,
+    exit_code INTEGER,
+    signal INTEGER,
+    log_path TEXT NOT NULL,
+    run_options TEXT NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    finished_at TIMESTAMP
+);
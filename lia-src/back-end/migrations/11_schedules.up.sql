@@ -0,0 +1,19 @@
+-- Stored commands run by `lia daemon`, on either a cron expression or a fixed interval.
+CREATE TABLE schedules (
+    id SERIAL PRIMARY KEY,
+    command_id UUID NOT NULL REFERENCES commands(id) ON DELETE CASCADE,
+    cron_expr TEXT,
+    interval_secs BIGINT,
+    catch_up TEXT NOT NULL DEFAULT 'skip',
+    next_run_at TIMESTAMP NOT NULL,
+    last_run_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL,
+    CHECK ((cron_expr IS NULL) <> (interval_secs IS NULL))
+);
+
+-- Each scheduled run is a job, so the jobs table doubles as the schedules' execution history.
+ALTER TABLE jobs
+ADD COLUMN schedule_id INTEGER REFERENCES schedules(id) ON DELETE SET NULL,
+ADD COLUMN scheduled_for TIMESTAMP;
+
+CREATE INDEX jobs_schedule_id_idx ON jobs (schedule_id);
@@ -0,0 +1,3 @@
+ALTER TABLE commands
+ADD COLUMN confirm BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN requires_root BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,7 @@
+ALTER TABLE commands
+ADD COLUMN cpu_limit_secs BIGINT,
+ADD COLUMN memory_limit_bytes BIGINT,
+ADD COLUMN open_files_limit BIGINT,
+ADD COLUMN processes_limit BIGINT,
+ADD COLUMN nice INTEGER,
+ADD COLUMN io_priority TEXT;
@@ -0,0 +1,18 @@
+-- Values referenced from commands as {{secret:name}}, encrypted with a key derived from a passphrase.
+CREATE TABLE secrets (
+    name TEXT PRIMARY KEY,
+    nonce BYTEA NOT NULL,
+    ciphertext BYTEA NOT NULL,
+    tag BYTEA NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL
+);
+
+-- A single row: the salt and rounds the key is derived with, and a value that tells whether a passphrase is right.
+CREATE TABLE secret_store (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    salt BYTEA NOT NULL,
+    iterations INTEGER NOT NULL,
+    verifier BYTEA NOT NULL,
+    created_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,20 @@
+ALTER TABLE commands
+ADD COLUMN retry_attempts INTEGER,
+ADD COLUMN retry_delay_ms BIGINT,
+ADD COLUMN retry_backoff TEXT,
+ADD COLUMN retry_max_delay_ms BIGINT,
+ADD COLUMN retry_on_exit_codes INTEGER[],
+ADD COLUMN retry_on_output TEXT;
+
+-- How each attempt of a job with a retry policy ended; the job itself records the last one.
+CREATE TABLE job_attempts (
+    job_id INTEGER NOT NULL REFERENCES jobs(id) ON DELETE CASCADE,
+    attempt INTEGER NOT NULL,
+    exit_code INTEGER,
+    signal INTEGER,
+    timed_out BOOLEAN NOT NULL,
+    succeeded BOOLEAN NOT NULL,
+    duration_ms BIGINT NOT NULL,
+    finished_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (job_id, attempt)
+);
@@ -0,0 +1,36 @@
+CREATE TABLE command_aliases (
+    alias VARCHAR PRIMARY KEY,
+    command_id UUID NOT NULL REFERENCES commands(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_command_aliases_command_id ON command_aliases(command_id);
+
+-- Names and aliases share a single namespace, so neither may shadow the other.
+CREATE FUNCTION check_alias_not_a_name() RETURNS trigger AS $$
+BEGIN
+  IF EXISTS (SELECT 1 FROM commands WHERE name = NEW.alias) THEN
+    RAISE EXCEPTION 'alias "%" is already used as a command name', NEW.alias
+      USING ERRCODE = 'unique_violation';
+  END IF;
+  RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER command_aliases_unique_across_names
+BEFORE INSERT OR UPDATE ON command_aliases
+FOR EACH ROW EXECUTE FUNCTION check_alias_not_a_name();
+
+CREATE FUNCTION check_name_not_an_alias() RETURNS trigger AS $$
+BEGIN
+  IF EXISTS (SELECT 1 FROM command_aliases WHERE alias = NEW.name) THEN
+    RAISE EXCEPTION 'name "%" is already used as an alias', NEW.name
+      USING ERRCODE = 'unique_violation';
+  END IF;
+  RETURN NEW;
+END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER commands_unique_across_aliases
+BEFORE INSERT OR UPDATE OF name ON commands
+FOR EACH ROW EXECUTE FUNCTION check_name_not_an_alias();
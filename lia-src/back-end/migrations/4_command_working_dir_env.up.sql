@@ -0,0 +1,3 @@
+ALTER TABLE commands
+ADD COLUMN working_dir TEXT,
+ADD COLUMN env_vars TEXT[];
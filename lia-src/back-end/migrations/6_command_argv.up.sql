@@ -0,0 +1,3 @@
+-- When set, the command is executed directly from this token list instead of through an interpreter.
+ALTER TABLE commands
+ADD COLUMN argv TEXT[];
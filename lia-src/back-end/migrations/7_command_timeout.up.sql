@@ -0,0 +1,2 @@
+ALTER TABLE commands
+ADD COLUMN timeout_ms BIGINT;
@@ -0,0 +1,2 @@
+ALTER TABLE commands
+ADD COLUMN tty BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- Non-zero exit codes that still count as a successful run (e.g. 1 for grep finding no match).
+ALTER TABLE commands
+ADD COLUMN ok_exit_codes INTEGER[];
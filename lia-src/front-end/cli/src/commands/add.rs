@@ -0,0 +1,107 @@
+use lia_core::{models::command::NewCommand, shell, LiaCore};
+
+use super::run::print_findings;
+use crate::CLIAddCommand;
+
+pub async fn add(lia_core: &LiaCore, add_cmd: CLIAddCommand) {
+    let tags_vec = add_cmd.tags.map(|t| t.split(',').map(|s| s.trim().to_string()).collect());
+    let argv = if add_cmd.argv {
+        match tokenize_for_argv(&add_cmd.command_text) {
+            Some(argv) => Some(argv),
+            None => return,
+        }
+    } else {
+        None
+    };
+    let command_text = add_cmd.command_text.clone();
+    let new_cmd = NewCommand {
+        name: add_cmd.name,
+        description: add_cmd.description,
+        command_text: add_cmd.command_text,
+        tags: tags_vec,
+        working_dir: add_cmd.cwd,
+        env_vars: format_env_vars(add_cmd.env),
+        interpreter: add_cmd.interpreter,
+        argv,
+        timeout_ms: add_cmd.timeout.map(|timeout| timeout.as_millis() as i64),
+        tty: add_cmd.tty,
+        confirm: add_cmd.confirm,
+        requires_root: add_cmd.requires_root,
+        cpu_limit_secs: add_cmd.limits.cpu_secs().map(|secs| secs as i64),
+        memory_limit_bytes: add_cmd.limits.memory.map(|bytes| bytes as i64),
+        open_files_limit: add_cmd.limits.open_files.map(|files| files as i64),
+        processes_limit: add_cmd.limits.max_procs.map(|processes| processes as i64),
+        nice: add_cmd.limits.nice,
+        io_priority: add_cmd.limits.ionice.filter(|ionice| !ionice.is_empty()),
+        retry_attempts: add_cmd.retry.attempts(),
+        retry_delay_ms: add_cmd.retry.retry_delay.map(|delay| delay.as_millis() as i64),
+        retry_backoff: add_cmd.retry.backoff.filter(|backoff| !backoff.is_empty()),
+        retry_max_delay_ms: add_cmd.retry.max_retry_delay.map(|delay| delay.as_millis() as i64),
+        retry_on_exit_codes: add_cmd.retry.retry_on_exit.filter(|codes| !codes.is_empty()),
+        retry_on_output: add_cmd.retry.retry_on_output.filter(|pattern| !pattern.is_empty()),
+        ok_exit_codes: Some(add_cmd.ok_exit_codes).filter(|codes| !codes.is_empty()),
+    };
+    match lia_core.add_command(new_cmd).await {
+        Ok(_) => {
+            println!("Command added successfully.");
+            warn_if_risky(lia_core, &command_text);
+        }
+        Err(e) => println!("Error adding command: {}", e),
+    }
+}
+
+/// Warns when a command being stored matches a risk rule, since it will ask for confirmation before each run.
+pub fn warn_if_risky(lia_core: &LiaCore, command_text: &str) {
+    match lia_core.analyze_risk(command_text) {
+        Ok(findings) if findings.is_empty() => {}
+        Ok(findings) => {
+            eprintln!("Warning: this command looks dangerous and will ask for confirmation before each run:");
+            print_findings(&findings);
+        }
+        Err(e) => eprintln!("Warning: could not check the command for risks: {}", e),
+    }
+}
+
+/// Tokenizes `command_text` for direct execution, asking before storing a command that needs a shell.
+pub fn tokenize_for_argv(command_text: &str) -> Option<Vec<String>> {
+    let argv = match shell::split(command_text) {
+        Ok(argv) => argv,
+        Err(e) => {
+            println!("Error converting command: {}", e);
+            return None;
+        }
+    };
+
+    let features = shell::shell_features(command_text);
+    if !features.is_empty() {
+        println!(
+            "Warning: this command uses shell features ({}) that won't work without a shell.",
+            features.join(", ")
+        );
+        println!("It would run as: {:?}", argv);
+        if !confirm("Store it as a direct exec command anyway? [y/N]") {
+            println!("Conversion cancelled.");
+            return None;
+        }
+    }
+    Some(argv)
+}
+
+fn confirm(prompt: &str) -> bool {
+    println!("{}", prompt);
+    let mut input = String::new();
+    if let Err(e) = std::io::stdin().read_line(&mut input) {
+        println!("Error reading input: {}", e);
+        return false;
+    }
+    let input = input.trim().to_lowercase();
+    input == "y" || input == "yes"
+}
+
+pub fn format_env_vars(env: Vec<(String, String)>) -> Option<Vec<String>> {
+    if env.is_empty() {
+        None
+    } else {
+        Some(env.into_iter().map(|(key, value)| format!("{}={}", key, value)).collect())
+    }
+}
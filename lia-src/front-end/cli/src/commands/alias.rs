@@ -0,0 +1,20 @@
+use lia_core::LiaCore;
+
+use crate::AliasAction;
+
+pub async fn alias(lia_core: &LiaCore, action: AliasAction) {
+    match action {
+        AliasAction::Add { name, aliases } => {
+            match lia_core.add_aliases(&name, &aliases).await {
+                Ok(_) => println!("Aliases added successfully."),
+                Err(e) => println!("Error adding aliases: {}", e),
+            }
+        }
+        AliasAction::Rm { aliases } => {
+            match lia_core.remove_aliases(&aliases).await {
+                Ok(_) => println!("Aliases removed successfully."),
+                Err(e) => println!("Error removing aliases: {}", e),
+            }
+        }
+    }
+}
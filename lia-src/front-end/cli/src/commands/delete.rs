@@ -0,0 +1,85 @@
+use lia_core::{errors::LiaCoreError, LiaCore};
+
+pub async fn delete(lia_core: &LiaCore, name: Option<String>, tags: Option<String>, all: bool) {
+    if name.is_none() && tags.is_none() && !all {
+        eprintln!("Error: You must provide either a name or tags to delete commands.");
+        return;
+    } else if all {
+        println!("Are you sure you want to delete all commands? [y/N]");
+        let mut input = String::new();
+        match std::io::stdin().read_line(&mut input).map_err(LiaCoreError::IoError) {
+            Ok(_) => (),
+            Err(e) => {
+                println!("Error reading input: {}", e);
+                return;
+            }
+        }
+        let input = input.trim().to_lowercase();
+
+        if input == "y" || input == "yes" {
+            match lia_core.delete_all_commands().await {
+                Ok(_) => (),
+                Err(_) => {
+                    println!("Error deleting commands.");
+                    return;
+                }
+            }
+            println!("All commands deleted successfully.");
+        } else {
+            println!("Deletion cancelled.");
+        }
+        return;
+    }
+
+    let tags_vec = tags.map(|t| {
+        t.split(',')
+            .map(|s| s.trim().to_string())
+            .collect::<Vec<String>>()
+    });
+
+    let commands_to_delete = match lia_core.find_commands_for_deletion(name.clone(), tags_vec.clone()).await {
+        Ok(c) => c,
+        Err(_) => {
+            println!("Error finding commands for deletion.");
+            return;
+        }
+    }; 
+
+    if commands_to_delete.is_empty() {
+        println!("No commands found matching the criteria for deletion.");
+        return;
+    }
+
+    println!("The following commands will be deleted:");
+    for cmd in &commands_to_delete {
+        println!("Name: {}", cmd.name);
+        println!("Description: {}", cmd.description.clone().unwrap_or_default());
+        println!("Command: {}", cmd.command_text);
+        println!("Tags: {:?}", cmd.tags.clone().unwrap_or_default());
+        println!("---");
+    }
+
+    println!("Are you sure you want to delete these commands? [y/N]");
+    let mut input = String::new();
+    match std::io::stdin().read_line(&mut input).map_err(LiaCoreError::IoError) {
+        Ok(_) => (),
+        Err(e) => {
+            println!("Error reading input: {}", e);
+            return;
+        }
+    }
+    let input = input.trim().to_lowercase();
+
+    if input == "y" || input == "yes" {
+        match lia_core.delete_commands(name, tags_vec).await {
+            Ok(_) => (),
+            Err(_) => {
+                println!("Error deleting commands.");
+                return;
+            }
+        }
+        println!("Commands deleted successfully.");
+    } else {
+        println!("Deletion cancelled.");
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    io::{stdout, Write},
+    time::Duration
+};
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType},
+};
+
+use lia_core::{models::command::Command, LiaCore};
+
+pub async fn list(lia_core: &LiaCore, limit: i64) {
+    match lia_core.get_all_commands(limit, 0).await {
+        Ok(commands) => {
+            display_commands_paginated(commands);
+        }
+        Err(e) => println!("Error retrieving commands: {}", e),
+    }
+}
+
+pub async fn search(lia_core: &LiaCore, query: Option<String>, tags: Option<String>, limit: i64) {
+    let tags_vec = tags.map(|t| {
+        t.split(',')
+            .map(|s| s.trim().to_string())
+            .collect::<Vec<String>>()
+    });
+
+    let query = query.unwrap_or_default();
+
+    let commands = match lia_core.search_commands(&query, tags_vec, limit, 0).await {
+        Ok(c) => c,
+        Err(_) => {
+            println!("Error searching for commands.");
+            return;
+        }
+    };
+    display_commands_paginated(commands);
+}
+
+fn display_commands_paginated(commands: Vec<Command>) {
+    const PAGE_SIZE: usize = 10;
+    let mut current_page: usize = 0;
+    let mut display = true;
+    let total_pages = if commands.is_empty() {
+        1
+    } else {
+        (commands.len() + PAGE_SIZE - 1) / PAGE_SIZE
+    };
+
+    if let Err(e) = enable_raw_mode() {
+        eprintln!("Error enabling raw mode: {}", e);
+        return;
+    }
+
+    let mut stdout = stdout();
+    if let Err(e) = execute!(stdout, Hide) {
+        eprintln!("Error hiding cursor: {}", e);
+    }
+
+    loop {
+        if display{
+            let start = current_page * PAGE_SIZE;
+            let end = std::cmp::min(start + PAGE_SIZE, commands.len());
+            let current_commands = &commands[start..end];
+
+            for (i, cmd) in current_commands.iter().enumerate() {
+                let command_number = start + i + 1;
+
+                if let Err(e) = write!(stdout, "Command {}\r\n", command_number) {
+                    eprintln!("Error writing to stdout: {}", e);
+                    break;
+                }
+                if let Err(e) = write!(stdout, "Name: {}\r\n", cmd.name) {
+                    eprintln!("Error writing to stdout: {}", e);
+                    break;
+                }
+                if !cmd.aliases.is_empty() {
+                    if let Err(e) = write!(stdout, "Aliases: {}\r\n", cmd.aliases.join(", ")) {
+                        eprintln!("Error writing to stdout: {}", e);
+                        break;
+                    }
+                }
+                if let Err(e) = write!(
+                    stdout,
+                    "Description: {}\r\n",
+                    cmd.description.clone().unwrap_or_default()
+                ) {
+                    eprintln!("Error writing to stdout: {}", e);
+                    break;
+                }
+                if let Err(e) = write!(stdout, "Command: {}\r\n", cmd.command_text) {
+                    eprintln!("Error writing to stdout: {}", e);
+                    break;
+                }
+                if let Some(argv) = &cmd.argv {
+                    if let Err(e) = write!(stdout, "Exec: {:?}\r\n", argv) {
+                        eprintln!("Error writing to stdout: {}", e);
+                        break;
+                    }
+                }
+                if let Some(interpreter) = &cmd.interpreter {
+                    if let Err(e) = write!(stdout, "Interpreter: {}\r\n", interpreter) {
+                        eprintln!("Error writing to stdout: {}", e);
+                        break;
+                    }
+                }
+                if let Some(timeout_ms) = cmd.timeout_ms {
+                    let timeout = Duration::from_millis(timeout_ms as u64);
+                    if let Err(e) = write!(stdout, "Timeout: {}\r\n", humantime::format_duration(timeout)) {
+                        eprintln!("Error writing to stdout: {}", e);
+                        break;
+                    }
+                }
+                if cmd.tty {
+                    if let Err(e) = write!(stdout, "TTY: yes\r\n") {
+                        eprintln!("Error writing to stdout: {}", e);
+                        break;
+                    }
+                }
+                if cmd.confirm {
+                    if let Err(e) = write!(stdout, "Confirm: yes\r\n") {
+                        eprintln!("Error writing to stdout: {}", e);
+                        break;
+                    }
+                }
+                if cmd.requires_root {
+                    if let Err(e) = write!(stdout, "Requires root: yes\r\n") {
+                        eprintln!("Error writing to stdout: {}", e);
+                        break;
+                    }
+                }
+                if let Some(codes) = &cmd.ok_exit_codes {
+                    let codes = codes.iter().map(|code| code.to_string()).collect::<Vec<_>>().join(", ");
+                    if let Err(e) = write!(stdout, "OK exit codes: {}\r\n", codes) {
+                        eprintln!("Error writing to stdout: {}", e);
+                        break;
+                    }
+                }
+                if let Some(limits) = cmd.resource_limits().ok().filter(|limits| !limits.is_empty()) {
+                    if let Err(e) = write!(stdout, "Limits: {}\r\n", limits) {
+                        eprintln!("Error writing to stdout: {}", e);
+                        break;
+                    }
+                }
+                if let Some(retry) = cmd.retry_policy().ok().filter(|retry| retry.is_enabled()) {
+                    if let Err(e) = write!(stdout, "Retries: {}\r\n", retry) {
+                        eprintln!("Error writing to stdout: {}", e);
+                        break;
+                    }
+                }
+                if let Some(dir) = &cmd.working_dir {
+                    if let Err(e) = write!(stdout, "Working dir: {}\r\n", dir) {
+                        eprintln!("Error writing to stdout: {}", e);
+                        break;
+                    }
+                }
+                if let Some(env_vars) = cmd.env_vars.as_ref().filter(|vars| !vars.is_empty()) {
+                    if let Err(e) = write!(stdout, "Env: {}\r\n", env_vars.join(" ")) {
+                        eprintln!("Error writing to stdout: {}", e);
+                        break;
+                    }
+                }
+                let tags: Vec<String> = cmd
+                    .tags
+                    .clone()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|tag| tag.clone())
+                    .collect();
+                if let Err(e) = write!(stdout, "Tags: {:?}\r\n", tags) {
+                    eprintln!("Error writing to stdout: {}", e);
+                    break;
+                }
+                if let Err(e) = write!(stdout, "---\r\n") {
+                    eprintln!("Error writing to stdout: {}", e);
+                    break;
+                }
+            }
+
+            if let Err(e) = write!(stdout, "Page {}/{}\r\n", current_page + 1, total_pages) {
+                eprintln!("Error writing to stdout: {}", e);
+                break;
+            }
+            if let Err(e) = write!(
+                stdout,
+                "Use Up/Down arrows to navigate, 'q' to quit.\r\n"
+            ) {
+                eprintln!("Error writing to stdout: {}", e);
+                break;
+            }
+            if let Err(e) = write!(stdout, "---\r\n") {
+                eprintln!("Error writing to stdout: {}", e);
+                break;
+            }
+
+            if let Err(e) = stdout.flush() {
+                eprintln!("Error flushing stdout: {}", e);
+                break;
+            }
+        }
+
+        match event::read() {
+            Ok(Event::Key(KeyEvent { code, .. })) => match code {
+                KeyCode::Char('q') | KeyCode::Esc => {
+                    break;
+                }
+                KeyCode::Down | KeyCode::Right | KeyCode::Enter => {
+                    if current_page + 1 < total_pages {
+                        display = true;
+                        current_page += 1;
+                    } else {
+                        display = false;
+                    }
+                }
+                _ => {
+                    display = false;
+                }
+            },
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("Error reading event: {}", e);
+                break;
+            }
+        }
+    }
+
+    if let Err(e) = execute!(stdout, Show) {
+        eprintln!("Error showing cursor: {}", e);
+    }
+    if let Err(e) = disable_raw_mode() {
+        eprintln!("Error disabling raw mode: {}", e);
+    }
+    if let Err(e) = execute!(stdout, Clear(ClearType::All), MoveTo(0, 0)) {
+        eprintln!("Error clearing screen: {}", e);
+    }
+}
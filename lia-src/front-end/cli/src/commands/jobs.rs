@@ -0,0 +1,138 @@
+use std::{
+    io::{stdout, Write},
+    time::Duration
+};
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+
+use lia_core::{
+    LiaCore,
+    errors::LiaCoreError,
+    models::{job::Job, retry::Attempt}
+};
+use system::Logger;
+
+use crate::JobsAction;
+
+pub async fn jobs(lia_core: &LiaCore, action: Option<JobsAction>, limit: i64) {
+    match action {
+        None => match lia_core.get_jobs(limit).await {
+            Ok(jobs) => display_jobs(&jobs),
+            Err(e) => println!("Error retrieving jobs: {}", e),
+        },
+        Some(JobsAction::Logs { id, follow }) => {
+            if let Err(e) = print_job_logs(lia_core, id, follow).await {
+                eprintln!("Error reading job logs: {}", e);
+                std::process::exit(1);
+            }
+        }
+        Some(JobsAction::Kill { id }) => match lia_core.kill_job(id).await {
+            Ok(_) => println!("Job {} is stopping.", id),
+            Err(e) => {
+                eprintln!("Error killing job: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Some(JobsAction::Wait { id }) => match lia_core.wait_job(id).await {
+            Ok(job) => {
+                println!("Job {} {}.", job.id, job.status.as_str());
+                std::process::exit(job.exit_status().unwrap_or(0));
+            }
+            Err(e) => {
+                eprintln!("Error waiting for job: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Some(JobsAction::Attempts { id }) => match lia_core.get_job_attempts(id).await {
+            Ok(attempts) => display_attempts(&attempts),
+            Err(e) => {
+                eprintln!("Error retrieving attempts: {}", e);
+                std::process::exit(1);
+            }
+        },
+        Some(JobsAction::Supervise { id }) => {
+            if let Err(e) = lia_core.supervise_job(id).await {
+                Logger::error(format!("Job {} failed: {}", id, e), true);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Jobs are run by a detached copy of LiA (`lia jobs supervise <id>`), so they outlive the process starting them.
+pub fn job_supervisor() -> std::io::Result<Vec<String>> {
+    let exe = std::env::current_exe()?;
+    Ok(vec![exe.to_string_lossy().to_string(), String::from("jobs"), String::from("supervise")])
+}
+
+pub fn format_local(time: NaiveDateTime) -> String {
+    Local.from_utc_datetime(&time).format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+pub fn display_jobs(jobs: &[Job]) {
+    if jobs.is_empty() {
+        println!("No jobs found.");
+        return;
+    }
+
+    println!("ID     STATUS     COMMAND                  STARTED              DURATION   EXIT");
+    for job in jobs {
+        let finished = job.finished_at.unwrap_or_else(|| Utc::now().naive_utc());
+        let duration = Duration::from_secs((finished - job.started_at).num_seconds().max(0) as u64);
+        let exit = job.exit_status().map(|code| code.to_string()).unwrap_or_default();
+        println!(
+            "{:<6} {:<10} {:<24} {:<20} {:<10} {}",
+            job.id,
+            job.status.as_str(),
+            job.command_name,
+            format_local(job.started_at),
+            humantime::format_duration(duration).to_string(),
+            exit
+        );
+    }
+}
+
+fn display_attempts(attempts: &[Attempt]) {
+    if attempts.is_empty() {
+        println!("No attempts recorded; the job has no retry policy or hasn't finished an attempt yet.");
+        return;
+    }
+
+    println!("ATTEMPT  RESULT  EXIT  DURATION   OUTCOME");
+    for attempt in attempts {
+        let duration = Duration::from_millis(attempt.duration.as_millis() as u64);
+        println!(
+            "{:<8} {:<7} {:<5} {:<10} {}",
+            attempt.number,
+            if attempt.succeeded { "pass" } else { "FAIL" },
+            attempt.exit_status(),
+            humantime::format_duration(duration).to_string(),
+            attempt
+        );
+    }
+}
+
+/// Prints the job's log; with `follow`, keeps printing what it appends until the job finishes.
+async fn print_job_logs(lia_core: &LiaCore, id: i32, follow: bool) -> Result<(), LiaCoreError> {
+    let job = lia_core.get_job(id).await?;
+    let mut log = match std::fs::File::open(&job.log_path) {
+        Ok(log) => log,
+        // The supervisor creates the log once it starts.
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && follow => {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            std::fs::File::open(&job.log_path)?
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut stdout = stdout();
+    loop {
+        // Checked before reading, so output written just before the job ended is still printed.
+        let finished = !follow || lia_core.get_job(id).await?.is_finished();
+        std::io::copy(&mut log, &mut stdout)?;
+        stdout.flush()?;
+        if finished {
+            return Ok(());
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
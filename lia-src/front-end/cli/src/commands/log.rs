@@ -0,0 +1,14 @@
+use lia_core::LiaCore;
+use system::SysConfigs;
+
+pub fn log(on: bool, off: bool) {
+    let is_root = LiaCore::is_sudo_user();
+    if !is_root {
+        println!("This command must be run with sudo.");
+        return;
+    }
+
+    let toggle = on || !off;
+    SysConfigs::set_log(toggle, false, None);
+    println!("Logging turned {}", if toggle { "on" } else { "off" });
+}
@@ -0,0 +1,11 @@
+pub mod add;
+pub mod alias;
+pub mod delete;
+pub mod get;
+pub mod jobs;
+pub mod log;
+pub mod run;
+pub mod schedule;
+pub mod secret;
+pub mod update;
+pub mod watch;
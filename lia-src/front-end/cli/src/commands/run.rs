@@ -0,0 +1,707 @@
+use std::{
+    io::{stdout, BufRead, IsTerminal, Write},
+    path::Path,
+    time::Duration
+};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    task
+};
+use chrono::Local;
+use regex::Regex;
+use crossterm::{
+    cursor::MoveTo,
+    style::{Color, Stylize},
+    execute,
+    terminal::{Clear, ClearType},
+};
+
+use lia_core::{
+    LiaCore,
+    diff,
+    dirs,
+    errors::LiaCoreError,
+    risk::RiskFinding,
+    models::{
+        command::Command,
+        retry::{self, Attempt},
+        run::{CancelHandle, ExecEvent, RenderedCommand, RunOptions, Sandbox, StdinSource}
+    },
+    shell
+};
+
+use super::{jobs::job_supervisor, secret::unlock_secrets_for};
+use crate::CLIRunCommand;
+
+pub async fn run(lia_core: &LiaCore, run_cmd: CLIRunCommand) {
+    let CLIRunCommand { names, tag, parallel, in_dirs, where_, dry_run, yes, cwd, env, timeout, tty, detach, every, count, until_exit, until_match, limits, retry, sandbox, writable, args } = run_cmd;
+    let sandbox = sandbox.then_some(Sandbox { writable });
+    if names.len() > 1 || tag.is_some() || parallel.is_some() || !in_dirs.is_empty() {
+        if tty || detach || every.is_some() {
+            eprintln!("Error: --tty, --detach and --every can only be used with a single command.");
+            std::process::exit(2);
+        }
+        let limit = match parallel {
+            None => 1,
+            Some(0) => std::thread::available_parallelism().map_or(4, |cpus| cpus.get()),
+            Some(limit) => limit,
+        };
+        let options = RunOptions {
+            cwd,
+            env,
+            timeout,
+            args,
+            limits: limits.to_limits(),
+            sandbox,
+            retry: retry.to_policy(),
+            ..RunOptions::default()
+        };
+
+        if in_dirs.is_empty() {
+            let cmds = match find_commands_to_run(lia_core, &names, tag).await {
+                Ok(cmds) => cmds,
+                Err(e) => {
+                    eprintln!("Error running commands: {}", e);
+                    std::process::exit(1);
+                }
+            };
+            let runs: Vec<_> = cmds.into_iter().map(|cmd| (cmd.name.clone(), cmd, options.clone())).collect();
+            if dry_run {
+                std::process::exit(print_dry_runs(lia_core, &runs));
+            }
+            if !yes {
+                confirm_runs(lia_core, &runs);
+            }
+            unlock_secrets_for(lia_core, &runs).await;
+            std::process::exit(run_parallel(lia_core, runs, "COMMAND", OutputLayout::Prefixed, limit).await);
+        }
+
+        if names.len() > 1 {
+            eprintln!("Error: --in-dirs runs a single command.");
+            std::process::exit(2);
+        }
+        let cmd = match lia_core.get_command_by_name(&names[0]).await {
+            Ok(cmd) => cmd,
+            Err(_) => {
+                eprintln!("Command not found.");
+                std::process::exit(1);
+            }
+        };
+        let dirs = match dirs::find_dirs(&in_dirs, where_.as_deref()) {
+            Ok(dirs) if dirs.is_empty() => {
+                eprintln!("No directories match.");
+                std::process::exit(1);
+            }
+            Ok(dirs) => dirs,
+            Err(e) => {
+                eprintln!("Error running command: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let runs: Vec<_> = dirs
+            .into_iter()
+            .map(|dir| (display_dir(&dir), cmd.clone(), RunOptions { cwd: Some(dir), ..options.clone() }))
+            .collect();
+        if dry_run {
+            std::process::exit(print_dry_runs(lia_core, &runs));
+        }
+        if !yes {
+            confirm_runs(lia_core, &runs);
+        }
+        unlock_secrets_for(lia_core, &runs).await;
+        std::process::exit(run_parallel(lia_core, runs, "DIRECTORY", OutputLayout::Grouped, limit).await);
+    }
+    let name = &names[0];
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+
+    let handle = task::spawn(async move {
+        while let Some(event) = rx.recv().await {
+            match event {
+                ExecEvent::Stdout(line) => println!("{}", line),
+                ExecEvent::Stderr(line) => eprintln!("{}", line),
+                ExecEvent::Exited { .. } => {}
+                ExecEvent::Retrying { attempt, max_attempts, delay } => {
+                    eprintln!("lia: {}", describe_retry(&attempt, max_attempts, delay));
+                }
+                ExecEvent::Attempts(attempts) => print_attempts("lia: ", &attempts),
+            }
+        }
+    });
+
+    let cmd = match lia_core.get_command_by_name(name).await {
+        Ok(cmd) => cmd,
+        Err(_) => {
+            eprintln!("Command not found.");
+            std::process::exit(1);
+        }
+    };
+
+    // Piped input (e.g. `cat data.json | lia run pretty-json`) is passed through; a terminal is not.
+    let stdin = if std::io::stdin().is_terminal() {
+        None
+    } else {
+        Some(StdinSource::Inherit)
+    };
+    let options = RunOptions { cwd, env, timeout, tty, stdin, args, limits: limits.to_limits(), sandbox, retry: retry.to_policy() };
+
+    let run = [(cmd.name.clone(), cmd, options)];
+    if dry_run {
+        std::process::exit(print_dry_runs(lia_core, &run));
+    }
+    if !yes {
+        confirm_runs(lia_core, &run);
+    }
+    unlock_secrets_for(lia_core, &run).await;
+    let [(_, cmd, options)] = run;
+
+    if let Some(every) = every {
+        let stop = EveryStop { count, until_exit, until_match };
+        let options = RunOptions { stdin: None, ..options };
+        std::process::exit(run_every(lia_core, cmd, options, every, stop).await);
+    }
+
+    if detach {
+        let supervisor = match job_supervisor() {
+            Ok(supervisor) => supervisor,
+            Err(e) => {
+                eprintln!("Error starting job: {}", e);
+                std::process::exit(1);
+            }
+        };
+        match lia_core.start_job(cmd, options, &supervisor).await {
+            Ok(job) => println!("Started job {} ({}). Output: {}", job.id, job.command_name, job.log_path),
+            Err(e) => {
+                eprintln!("Error starting job: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let run = match lia_core.start_command_stream(cmd, options, tx) {
+        Ok(run) => run,
+        Err(e) => {
+            eprintln!("Error running command: {}", e);
+            std::process::exit(e.exit_code());
+        }
+    };
+
+    // The command runs in its own process group, so Ctrl-C reaches LiA only and is forwarded as a cancellation.
+    let cancel = run.cancel_handle();
+    task::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+        }
+    });
+
+    let result = run.wait().await;
+    handle.await.expect("Failed to join output task");
+    // Exit like the command did (128+signal if it was killed), so `lia run` can be used in scripts and CI.
+    if let Err(e) = result {
+        eprintln!("Error running command: {}", e);
+        std::process::exit(e.exit_code());
+    }
+}
+
+/// Colors told apart on both dark and light terminals, cycled through for `lia run --parallel` prefixes.
+const PREFIX_COLORS: [Color; 6] = [Color::Cyan, Color::Magenta, Color::Yellow, Color::Green, Color::Blue, Color::Red];
+
+async fn find_commands_to_run(lia_core: &LiaCore, names: &[String], tag: Option<String>) -> Result<Vec<Command>, LiaCoreError> {
+    if let Some(tag) = tag {
+        let tags = tag.split(',').map(|s| s.trim().to_string()).collect();
+        let cmds = lia_core.search_commands("", Some(tags), i64::MAX, 0).await?;
+        if cmds.is_empty() {
+            return Err(LiaCoreError::InvalidInputError(format!("No commands tagged {}", tag)));
+        }
+        return Ok(cmds);
+    }
+
+    let mut cmds = Vec::with_capacity(names.len());
+    for name in names {
+        cmds.push(lia_core.get_command_by_name(name).await?);
+    }
+    Ok(cmds)
+}
+
+/// How `run_parallel` shows the output of the runs.
+enum OutputLayout {
+    /// Each line as soon as it is printed, prefixed with its run's label.
+    Prefixed,
+    /// One block per run, in order, under a header with its label. Output of later runs is held back until the
+    /// runs before them finish.
+    Grouped,
+}
+
+/// Runs labelled commands at most `limit` at a time, then prints a pass/fail summary whose first column is `heading`.
+/// Returns 0 if every run succeeded, otherwise the exit status of the first that failed.
+async fn run_parallel(
+    lia_core: &LiaCore,
+    runs: Vec<(String, Command, RunOptions)>,
+    heading: &str,
+    layout: OutputLayout,
+    limit: usize,
+) -> i32 {
+    let (labels, runs): (Vec<String>, Vec<_>) = runs.into_iter().map(|(label, cmd, options)| (label, (cmd, options))).unzip();
+    let width = labels.iter().map(|label| label.len()).max().unwrap_or(0).max(heading.len());
+    let colored = stdout().is_terminal();
+    let prefixes: Vec<String> = labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| match layout {
+            OutputLayout::Prefixed => {
+                let prefix = format!("{:<width$} | ", label, width = width);
+                if colored {
+                    prefix.with(PREFIX_COLORS[index % PREFIX_COLORS.len()]).to_string()
+                } else {
+                    prefix
+                }
+            }
+            OutputLayout::Grouped => String::new(),
+        })
+        .collect();
+    let headers: Vec<String> = labels
+        .iter()
+        .map(|label| {
+            let header = format!("==> {} <==", label);
+            if colored {
+                header.bold().to_string()
+            } else {
+                header
+            }
+        })
+        .collect();
+
+    // Runs with retries are over at their `ExecEvent::Attempts`, not at the end of the first attempt.
+    let retrying: Vec<bool> = runs
+        .iter()
+        .map(|(cmd, options)| lia_core.render_command(cmd, options).is_ok_and(|rendered| rendered.retry.is_enabled()))
+        .collect();
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<(usize, ExecEvent)>();
+    let printer = task::spawn(async move {
+        let print = |index: usize, event: &ExecEvent| match event {
+            ExecEvent::Stdout(line) => println!("{}{}", prefixes[index], line),
+            ExecEvent::Stderr(line) => eprintln!("{}{}", prefixes[index], line),
+            ExecEvent::Retrying { attempt, max_attempts, delay } => {
+                eprintln!("{}lia: {}", prefixes[index], describe_retry(attempt, *max_attempts, *delay));
+            }
+            ExecEvent::Exited { .. } | ExecEvent::Attempts(_) => {}
+        };
+        let grouped = matches!(layout, OutputLayout::Grouped);
+        // Summed over every attempt.
+        let mut durations: Vec<Option<Duration>> = vec![None; headers.len()];
+        let mut attempts = vec![1; headers.len()];
+        let mut finished = vec![false; headers.len()];
+        let mut held: Vec<Vec<ExecEvent>> = vec![Vec::new(); headers.len()];
+        // The run whose output is shown live when grouping.
+        let mut current = 0;
+        if grouped && !headers.is_empty() {
+            println!("{}", headers[0]);
+        }
+
+        while let Some((index, event)) = rx.recv().await {
+            match &event {
+                ExecEvent::Exited { duration, .. } => {
+                    durations[index] = Some(durations[index].unwrap_or(Duration::ZERO) + *duration);
+                    finished[index] = !retrying[index];
+                }
+                ExecEvent::Attempts(list) => {
+                    attempts[index] = list.len();
+                    finished[index] = true;
+                }
+                _ => {}
+            }
+            if !grouped || index == current {
+                print(index, &event);
+            } else {
+                held[index].push(event);
+            }
+
+            while grouped && current + 1 < headers.len() && finished[current] {
+                current += 1;
+                println!("\n{}", headers[current]);
+                for event in held[current].drain(..) {
+                    print(current, &event);
+                }
+            }
+        }
+
+        // Runs that never started don't report a duration; show whatever the rest printed.
+        if grouped {
+            for (index, events) in held.iter().enumerate().skip(current + 1) {
+                println!("\n{}", headers[index]);
+                for event in events {
+                    print(index, event);
+                }
+            }
+        }
+        (durations, attempts)
+    });
+
+    let cancel = CancelHandle::new();
+    let ctrl_c_cancel = cancel.clone();
+    task::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            ctrl_c_cancel.cancel();
+        }
+    });
+
+    let results = lia_core.run_commands_parallel(runs, limit, tx, &cancel).await;
+    let (durations, attempts) = printer.await.unwrap_or_default();
+
+    println!();
+    println!("{:<width$}  RESULT  EXIT  DURATION", heading, width = width);
+    for (index, result) in results.iter().enumerate() {
+        let (outcome, exit, mut note) = match result {
+            Ok(_) => ("pass", 0, String::new()),
+            // The exit status says it all for commands that ran and failed.
+            Err(e @ LiaCoreError::CommandExecutionError { code: Some(_), .. }) => ("FAIL", e.exit_code(), String::new()),
+            Err(e) => ("FAIL", e.exit_code(), e.to_string()),
+        };
+        if let Some(count @ 2..) = attempts.get(index).copied() {
+            note = if note.is_empty() { format!("{} attempts", count) } else { format!("{} attempts; {}", count, note) };
+        }
+        let outcome = match (colored, outcome) {
+            (false, _) => outcome.to_string(),
+            (true, "pass") => outcome.green().to_string(),
+            (true, _) => outcome.red().to_string(),
+        };
+        let duration = durations
+            .get(index)
+            .copied()
+            .flatten()
+            .map(|duration| humantime::format_duration(Duration::from_millis(duration.as_millis() as u64)).to_string())
+            .unwrap_or_else(|| String::from("-"));
+        let row = format!("{:<width$}  {}    {:<4}  {:<9} {}", labels[index], outcome, exit, duration, note, width = width);
+        println!("{}", row.trim_end());
+    }
+
+    if cancel.is_cancelled() {
+        return 130;
+    }
+    results.iter().find_map(|result| result.as_ref().err()).map_or(0, LiaCoreError::exit_code)
+}
+
+fn print_dry_runs(lia_core: &LiaCore, runs: &[(String, Command, RunOptions)]) -> i32 {
+    let mut exit = 0;
+    for (index, (label, cmd, options)) in runs.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        if runs.len() > 1 {
+            println!("==> {} <==", label);
+        }
+        match lia_core.render_command(cmd, options).and_then(|rendered| {
+            Ok((rendered, lia_core.assess_run_risk(cmd, options)?))
+        }) {
+            Ok((rendered, findings)) => print_rendered(&rendered, &findings),
+            Err(e) => {
+                eprintln!("Error rendering command: {}", e);
+                exit = e.exit_code();
+            }
+        }
+    }
+    exit
+}
+
+/// Asks before running commands stored with `confirm` or matching a risk rule, and exits unless the user agrees.
+/// Risky commands need their name typed, others a y/N answer. The answer is read from the terminal, since stdin
+/// may be piped into the commands.
+pub fn confirm_runs(lia_core: &LiaCore, runs: &[(String, Command, RunOptions)]) {
+    let mut risky: Vec<(&str, Vec<RiskFinding>)> = Vec::new();
+    let mut confirm: Vec<&str> = Vec::new();
+    for (_, cmd, options) in runs {
+        // Runs of one command in several directories share the same text.
+        if risky.iter().any(|(name, _)| *name == cmd.name) || confirm.contains(&cmd.name.as_str()) {
+            continue;
+        }
+        match lia_core.assess_run_risk(cmd, options) {
+            Ok(findings) if findings.is_empty() => {
+                if cmd.confirm {
+                    confirm.push(&cmd.name);
+                }
+            }
+            Ok(findings) => risky.push((&cmd.name, findings)),
+            Err(e) => {
+                eprintln!("Error running command: {}", e);
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+
+    for (name, findings) in &risky {
+        eprintln!("Warning: {} looks dangerous:", name);
+        print_findings(findings);
+    }
+    let agreed = match (risky.as_slice(), confirm.is_empty()) {
+        ([], true) => return,
+        ([], false) => {
+            let answer = ask_terminal(&format!("Run {}? [y/N] ", confirm.join(", ")));
+            matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+        }
+        ([(name, _)], true) => ask_terminal(&format!("Type '{}' to run anyway: ", name)).trim() == *name,
+        _ => ask_terminal("Type 'yes' to run them anyway: ").trim() == "yes",
+    };
+    if !agreed {
+        eprintln!("Aborted.");
+        std::process::exit(1);
+    }
+}
+
+/// Prompts on the terminal and returns the answer, exiting when there is no terminal to ask on.
+pub fn ask_terminal(prompt: &str) -> String {
+    let mut terminal = match std::fs::OpenOptions::new().read(true).write(true).open("/dev/tty") {
+        Ok(terminal) => terminal,
+        Err(_) => {
+            eprintln!("Refusing to run without confirmation. Pass --yes to run anyway.");
+            std::process::exit(1);
+        }
+    };
+    let _ = write!(terminal, "{}", prompt);
+    let _ = terminal.flush();
+    let mut answer = String::new();
+    let _ = std::io::BufReader::new(terminal).read_line(&mut answer);
+    answer
+}
+
+pub fn print_findings(findings: &[RiskFinding]) {
+    for finding in findings {
+        eprintln!("  - {}: {} ({})", finding.rule, finding.reason, finding.matched);
+    }
+}
+
+/// Shows what `lia run --dry-run` would run.
+fn print_rendered(rendered: &RenderedCommand, findings: &[RiskFinding]) {
+    println!("Command:       {}", rendered.name);
+    match (&rendered.interpreter, &rendered.script) {
+        (Some(interpreter), Some(script)) => {
+            println!("Interpreter:   {}", interpreter);
+            let mut lines = script.lines();
+            println!("Script:        {}", lines.next().unwrap_or_default());
+            for line in lines {
+                println!("               {}", line);
+            }
+        }
+        _ => println!("Interpreter:   none (runs the program directly)"),
+    }
+    println!("Runs:          {}", shell::join(&rendered.argv));
+    if let Some(user) = &rendered.user {
+        println!("User:          {}", user);
+    }
+    let missing = if rendered.cwd.is_dir() { "" } else { " (does not exist)" };
+    println!("Directory:     {}{}", rendered.cwd.display(), missing);
+
+    if rendered.env.is_empty() {
+        println!("Environment:   unchanged");
+    }
+    for (index, (key, value)) in rendered.env.iter().enumerate() {
+        let label = if index == 0 { "Environment:" } else { "" };
+        let current = match std::env::var(key) {
+            Ok(current) if current == *value => String::from(" (same as current)"),
+            Ok(current) => format!(" (currently {})", current),
+            Err(_) => String::new(),
+        };
+        println!("{:<14} {}={}{}", label, key, value, current);
+    }
+
+    if let Some(timeout) = rendered.timeout {
+        println!("Timeout:       {}", humantime::format_duration(timeout));
+    }
+    if rendered.tty {
+        println!("Terminal:      yes");
+    }
+    if !rendered.ok_exit_codes.is_empty() {
+        let codes: Vec<String> = rendered.ok_exit_codes.iter().map(|code| code.to_string()).collect();
+        println!("OK exit codes: {}", codes.join(", "));
+    }
+    if !rendered.limits.is_empty() {
+        println!("Limits:        {}", rendered.limits);
+    }
+    if rendered.retry.is_enabled() {
+        println!("Retries:       {}", rendered.retry);
+    }
+    if let Some(sandbox) = &rendered.sandbox {
+        let writable: Vec<String> = sandbox.writable.iter().map(|dir| dir.display().to_string()).collect();
+        if writable.is_empty() {
+            println!("Sandbox:       no network, read-only filesystem");
+        } else {
+            println!("Sandbox:       no network, read-only filesystem except {}", writable.join(", "));
+        }
+    }
+
+    if findings.is_empty() {
+        println!("Risk:          none found");
+    }
+    for (index, finding) in findings.iter().enumerate() {
+        let label = if index == 0 { "Risk:" } else { "" };
+        println!("{:<14} {}: {} ({})", label, finding.rule, finding.reason, finding.matched);
+    }
+}
+
+/// `path` with the home directory shown as `~`.
+fn display_dir(path: &Path) -> String {
+    match std::env::var_os("HOME").and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(relative) if relative.as_os_str().is_empty() => String::from("~"),
+        Some(relative) => format!("~/{}", relative.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// When `lia run --every` stops on its own.
+struct EveryStop {
+    count: Option<u64>,
+    until_exit: Option<i32>,
+    until_match: Option<Regex>,
+}
+
+/// Reruns `cmd` every `every` like watch(1), redrawing its output with changed lines highlighted.
+/// Returns LiA's exit status: 0 when a stop condition is met, the last run's status after `count` runs.
+async fn run_every(lia_core: &LiaCore, cmd: Command, options: RunOptions, every: Duration, stop: EveryStop) -> i32 {
+    let mut interrupt = match signal(SignalKind::interrupt()) {
+        Ok(interrupt) => interrupt,
+        Err(e) => {
+            eprintln!("Error running command: {}", e);
+            return 1;
+        }
+    };
+    let redraw = stdout().is_terminal();
+    let mut previous: Vec<String> = Vec::new();
+    let mut iteration = 0;
+
+    loop {
+        iteration += 1;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let run = match lia_core.start_command_stream(cmd.clone(), options.clone(), tx) {
+            Ok(run) => run,
+            Err(e) => {
+                eprintln!("Error running command: {}", e);
+                return e.exit_code();
+            }
+        };
+
+        let cancel = run.cancel_handle();
+        let mut lines = Vec::new();
+        let mut status = (None, None);
+        let mut interrupted = false;
+        loop {
+            tokio::select! {
+                event = rx.recv() => match event {
+                    Some(ExecEvent::Stdout(line)) | Some(ExecEvent::Stderr(line)) => lines.push(line),
+                    Some(ExecEvent::Exited { code, signal, .. }) => status = (code, signal),
+                    // Only the last attempt's output is shown.
+                    Some(ExecEvent::Retrying { .. }) => lines.clear(),
+                    Some(ExecEvent::Attempts(_)) => {}
+                    None => break,
+                },
+                _ = interrupt.recv(), if !interrupted => {
+                    cancel.cancel();
+                    interrupted = true;
+                }
+            }
+        }
+        let result = run.wait().await;
+        if interrupted {
+            return 130;
+        }
+
+        let changed = if iteration == 1 {
+            vec![false; lines.len()]
+        } else {
+            diff::changed_lines(&previous, &lines)
+        };
+        draw_every(&cmd.name, every, iteration, status, &lines, &changed, redraw);
+
+        let exit_code = match status {
+            (Some(code), _) => code,
+            (None, Some(signal)) => 128 + signal,
+            (None, None) => result.as_ref().err().map_or(0, LiaCoreError::exit_code),
+        };
+        if stop.until_exit == Some(exit_code)
+            || stop.until_match.as_ref().is_some_and(|regex| lines.iter().any(|line| regex.is_match(line)))
+        {
+            return 0;
+        }
+        if stop.count == Some(iteration) {
+            return result.err().map_or(0, |e| e.exit_code());
+        }
+
+        previous = lines;
+        tokio::select! {
+            _ = tokio::time::sleep(every) => {}
+            _ = interrupt.recv() => return 130,
+        }
+    }
+}
+
+/// Redraws the screen with the latest output, cut to the terminal's height like watch(1).
+/// Without a terminal, each run's output is printed below the previous one.
+fn draw_every(
+    name: &str,
+    every: Duration,
+    iteration: u64,
+    status: (Option<i32>, Option<i32>),
+    lines: &[String],
+    changed: &[bool],
+    redraw: bool,
+) {
+    let mut stdout = stdout();
+    let mut visible = lines.len();
+    if redraw {
+        let _ = execute!(stdout, Clear(ClearType::All), MoveTo(0, 0));
+        // Some terminals (e.g. under `script`) report no size; show everything then.
+        if let Ok((_, rows @ 4..)) = crossterm::terminal::size() {
+            visible = visible.min(rows as usize - 3);
+        }
+    }
+
+    let outcome = match status {
+        (Some(code), _) => format!("exit {}", code),
+        (None, Some(signal)) => format!("signal {}", signal),
+        (None, None) => String::from("no status"),
+    };
+    let header = format!(
+        "Every {}: {}    run {} · {} · {}",
+        humantime::format_duration(every),
+        name,
+        iteration,
+        outcome,
+        Local::now().format("%H:%M:%S")
+    );
+    if redraw {
+        println!("{}\n", header.bold());
+    } else {
+        println!("{}\n", header);
+    }
+
+    for (line, changed) in lines.iter().zip(changed).take(visible) {
+        if *changed && redraw {
+            println!("{}", line.as_str().reverse());
+        } else {
+            println!("{}", line);
+        }
+    }
+    if !redraw {
+        println!();
+    }
+}
+
+pub fn describe_retry(attempt: &Attempt, max_attempts: u32, delay: Duration) -> String {
+    format!(
+        "attempt {} of {} failed ({}), retrying in {}",
+        attempt.number,
+        max_attempts,
+        attempt,
+        humantime::format_duration(delay)
+    )
+}
+
+/// Prints how each attempt ended, once a run has been retried.
+pub fn print_attempts(prefix: &str, attempts: &[Attempt]) {
+    if attempts.len() < 2 {
+        return;
+    }
+    for line in retry::summarize(attempts) {
+        eprintln!("{}{}", prefix, line);
+    }
+}
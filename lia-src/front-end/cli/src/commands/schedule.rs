@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use lia_core::{
+    LiaCore,
+    models::{
+        run::RunOptions,
+        schedule::{NewSchedule, Schedule}
+    }
+};
+
+use super::{jobs::{display_jobs, format_local, job_supervisor}, run::{ask_terminal, print_findings}};
+use crate::ScheduleAction;
+
+pub async fn schedule(lia_core: &LiaCore, action: ScheduleAction) {
+    match action {
+        ScheduleAction::Add { name, cron, every, catch_up, yes } => {
+            if !yes {
+                confirm_schedule(lia_core, &name).await;
+            }
+            let new_schedule = NewSchedule {
+                command_name: name,
+                cron_expr: cron,
+                interval_secs: every.map(|every| every.as_secs() as i64),
+                catch_up,
+            };
+            match lia_core.add_schedule(new_schedule).await {
+                Ok(schedule) => println!(
+                    "Schedule {} added. Next run: {}",
+                    schedule.id,
+                    format_local(schedule.next_run_at)
+                ),
+                Err(e) => println!("Error adding schedule: {}", e),
+            }
+        }
+        ScheduleAction::List => match lia_core.get_schedules().await {
+            Ok(schedules) => display_schedules(&schedules),
+            Err(e) => println!("Error retrieving schedules: {}", e),
+        },
+        ScheduleAction::Rm { id } => match lia_core.remove_schedule(id).await {
+            Ok(_) => println!("Schedule removed successfully."),
+            Err(e) => println!("Error removing schedule: {}", e),
+        },
+        ScheduleAction::History { id, limit } => match lia_core.get_schedule_history(id, limit).await {
+            Ok(jobs) => display_jobs(&jobs),
+            Err(e) => println!("Error retrieving schedule history: {}", e),
+        },
+    }
+}
+
+pub async fn daemon(lia_core: &LiaCore) {
+    let supervisor = match job_supervisor() {
+        Ok(supervisor) => supervisor,
+        Err(e) => {
+            eprintln!("Error starting daemon: {}", e);
+            std::process::exit(1);
+        }
+    };
+    println!("Daemon running. Press Ctrl-C to stop; jobs already started keep running.");
+    if let Err(e) = lia_core.run_daemon(&supervisor).await {
+        eprintln!("Error running daemon: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// The daemon runs scheduled commands without asking, so scheduling a command stored with `confirm` is refused and
+/// one matching a risk rule needs its name typed; exits otherwise.
+async fn confirm_schedule(lia_core: &LiaCore, name: &str) {
+    let (cmd, findings) = match lia_core.get_command_by_name(name).await.and_then(|cmd| {
+        let findings = lia_core.assess_run_risk(&cmd, &RunOptions::default())?;
+        Ok((cmd, findings))
+    }) {
+        Ok(assessed) => assessed,
+        Err(e) => {
+            eprintln!("Error adding schedule: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if cmd.confirm {
+        eprintln!("Error adding schedule: {} asks for confirmation before every run. Pass --yes to schedule it anyway.", name);
+        std::process::exit(1);
+    }
+    if findings.is_empty() {
+        return;
+    }
+    eprintln!("Warning: {} looks dangerous and would run unattended:", name);
+    print_findings(&findings);
+    if ask_terminal(&format!("Type '{}' to schedule it anyway: ", name)).trim() != name {
+        eprintln!("Aborted.");
+        std::process::exit(1);
+    }
+}
+
+fn display_schedules(schedules: &[Schedule]) {
+    if schedules.is_empty() {
+        println!("No schedules found.");
+        return;
+    }
+
+    println!("ID     COMMAND                  SCHEDULE           CATCH-UP  NEXT RUN             LAST RUN");
+    for schedule in schedules {
+        let when = match (&schedule.cron_expr, schedule.interval_secs) {
+            (Some(expr), _) => expr.clone(),
+            (_, Some(secs)) => format!("every {}", humantime::format_duration(Duration::from_secs(secs as u64))),
+            (None, None) => String::new(),
+        };
+        println!(
+            "{:<6} {:<24} {:<18} {:<9} {:<20} {}",
+            schedule.id,
+            schedule.command_name,
+            when,
+            schedule.catch_up.as_str(),
+            format_local(schedule.next_run_at),
+            schedule.last_run_at.map(format_local).unwrap_or_default()
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::io::{IsTerminal, Write};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+
+use lia_core::{
+    LiaCore,
+    secrets::PASSPHRASE_ENV,
+    models::{command::Command, run::RunOptions, secret::Secret}
+};
+
+use super::jobs::format_local;
+use crate::SecretAction;
+
+pub async fn secret(lia_core: &LiaCore, action: SecretAction) {
+    match action {
+        SecretAction::Set { name } => {
+            unlock_secrets(lia_core).await;
+            let value = if std::io::stdin().is_terminal() {
+                read_hidden(&format!("Value for {}: ", name))
+            } else {
+                let mut value = String::new();
+                std::io::Read::read_to_string(&mut std::io::stdin(), &mut value)
+                    .ok()
+                    .map(|_| value.strip_suffix('\n').unwrap_or(&value).to_string())
+            };
+            let Some(value) = value else {
+                eprintln!("Error saving secret: no value given.");
+                std::process::exit(1);
+            };
+            match lia_core.set_secret(&name, &value).await {
+                Ok(_) => println!("Secret saved."),
+                Err(e) => {
+                    eprintln!("Error saving secret: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        SecretAction::Get { name } => {
+            unlock_secrets(lia_core).await;
+            match lia_core.get_secret(&name) {
+                Ok(value) => println!("{}", value),
+                Err(e) => {
+                    eprintln!("Error reading secret: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        SecretAction::Rm { name } => match lia_core.remove_secret(&name).await {
+            Ok(_) => println!("Secret removed."),
+            Err(e) => {
+                eprintln!("Error removing secret: {}", e);
+                std::process::exit(1);
+            }
+        },
+        SecretAction::List => match lia_core.get_secrets().await {
+            Ok(secrets) => display_secrets(&secrets),
+            Err(e) => {
+                eprintln!("Error listing secrets: {}", e);
+                std::process::exit(1);
+            }
+        },
+    }
+}
+
+/// Unlocks the secret store if any of `runs` references secrets.
+pub async fn unlock_secrets_for(lia_core: &LiaCore, runs: &[(String, Command, RunOptions)]) {
+    let needed = runs.iter().any(|(_, cmd, options)| lia_core.uses_secrets(cmd, options).unwrap_or(false));
+    if needed && !lia_core.secrets_unlocked() {
+        unlock_secrets(lia_core).await;
+    }
+}
+
+/// Unlocks the secret store with `LIA_SECRET_PASSPHRASE`, or a passphrase typed on the terminal; exits if
+/// neither works. Creating the store asks for the passphrase twice.
+async fn unlock_secrets(lia_core: &LiaCore) {
+    let passphrase = match std::env::var(PASSPHRASE_ENV) {
+        Ok(passphrase) => passphrase,
+        Err(_) => match lia_core.has_secret_store().await {
+            Ok(true) => read_hidden("Secret store passphrase: ").unwrap_or_default(),
+            Ok(false) => {
+                eprintln!("Creating the secret store. Its passphrase can't be recovered, so keep it safe.");
+                let passphrase = read_hidden("New passphrase: ").unwrap_or_default();
+                if read_hidden("Repeat the passphrase: ").unwrap_or_default() != passphrase {
+                    eprintln!("Error unlocking secrets: the passphrases don't match.");
+                    std::process::exit(1);
+                }
+                passphrase
+            }
+            Err(e) => {
+                eprintln!("Error unlocking secrets: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    if passphrase.is_empty() {
+        eprintln!("Error unlocking secrets: no passphrase given (set {} when there is no terminal).", PASSPHRASE_ENV);
+        std::process::exit(1);
+    }
+    if let Err(e) = lia_core.unlock_secrets(&passphrase).await {
+        eprintln!("Error unlocking secrets: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Reads a line from the terminal without echoing it. `None` without a terminal or when cancelled.
+fn read_hidden(prompt: &str) -> Option<String> {
+    let mut terminal = std::fs::OpenOptions::new().read(true).write(true).open("/dev/tty").ok()?;
+    let _ = write!(terminal, "{}", prompt);
+    let _ = terminal.flush();
+
+    enable_raw_mode().ok()?;
+    let mut input = Vec::new();
+    let mut byte = [0u8; 1];
+    let entered = loop {
+        match std::io::Read::read(&mut terminal, &mut byte) {
+            Ok(1) => match byte[0] {
+                b'\r' | b'\n' => break true,
+                // Ctrl-C, Ctrl-D
+                3 | 4 => break false,
+                // Backspace removes a whole character, not just its last byte.
+                8 | 127 => while let Some(byte) = input.pop() {
+                    if byte & 0xC0 != 0x80 {
+                        break;
+                    }
+                },
+                byte => input.push(byte),
+            },
+            _ => break false,
+        }
+    };
+    let _ = disable_raw_mode();
+    let _ = write!(terminal, "\r\n");
+    entered.then(|| String::from_utf8_lossy(&input).into_owned())
+}
+
+fn display_secrets(secrets: &[Secret]) {
+    if secrets.is_empty() {
+        println!("No secrets stored.");
+        return;
+    }
+
+    println!("NAME                             CREATED              UPDATED");
+    for secret in secrets {
+        println!(
+            "{:<32} {:<20} {}",
+            secret.name,
+            format_local(secret.created_at),
+            format_local(secret.updated_at)
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use lia_core::{models::command::UpdateCommand, LiaCore};
+
+use super::add::{format_env_vars, tokenize_for_argv, warn_if_risky};
+use crate::CLIUpdateCommand;
+
+pub async fn update(lia_core: &LiaCore, update_cmd: CLIUpdateCommand) {
+    let tags_vec = update_cmd.tags.map(|t| t.split(',').map(|s| s.trim().to_string()).collect());
+    let new_argv = if update_cmd.to_argv {
+        let command_text = match &update_cmd.command_text {
+            Some(text) => text.clone(),
+            None => match lia_core.get_command_by_name(&update_cmd.name).await {
+                Ok(cmd) => cmd.command_text,
+                Err(e) => {
+                    println!("Error updating command: {}", e);
+                    return;
+                }
+            },
+        };
+        match tokenize_for_argv(&command_text) {
+            Some(argv) => Some(argv),
+            None => return,
+        }
+    } else if update_cmd.to_shell {
+        Some(vec![])
+    } else {
+        None
+    };
+    let command_text = update_cmd.command_text.clone();
+    let new_cmd = UpdateCommand {
+        name: update_cmd.name,
+        new_command_text: update_cmd.command_text,
+        new_description: update_cmd.description,
+        new_tags: tags_vec,
+        new_working_dir: update_cmd.cwd,
+        new_env_vars: format_env_vars(update_cmd.env),
+        new_interpreter: update_cmd.interpreter,
+        new_argv,
+        new_timeout_ms: update_cmd.timeout.map(|timeout| timeout.as_millis() as i64),
+        new_tty: update_cmd.tty,
+        new_confirm: update_cmd.confirm,
+        new_requires_root: update_cmd.requires_root,
+        new_cpu_limit_secs: update_cmd.limits.cpu_secs().map(|secs| secs as i64),
+        new_memory_limit_bytes: update_cmd.limits.memory.map(|bytes| bytes as i64),
+        new_open_files_limit: update_cmd.limits.open_files.map(|files| files as i64),
+        new_processes_limit: update_cmd.limits.max_procs.map(|processes| processes as i64),
+        new_nice: update_cmd.limits.nice,
+        new_io_priority: update_cmd.limits.ionice,
+        new_retry_attempts: update_cmd.retry.attempts(),
+        new_retry_delay_ms: update_cmd.retry.retry_delay.map(|delay| delay.as_millis() as i64),
+        new_retry_backoff: update_cmd.retry.backoff,
+        new_retry_max_delay_ms: update_cmd.retry.max_retry_delay.map(|delay| delay.as_millis() as i64),
+        new_retry_on_exit_codes: update_cmd.retry.retry_on_exit,
+        new_retry_on_output: update_cmd.retry.retry_on_output,
+        new_ok_exit_codes: update_cmd.ok_exit_codes,
+    };
+    match lia_core.update_command(new_cmd).await {
+        Ok(_) => {
+            println!("Command updated successfully.");
+            if let Some(command_text) = command_text {
+                warn_if_risky(lia_core, &command_text);
+            }
+        }
+        Err(e) => println!("Error updating command: {}", e),
+    }
+}
@@ -0,0 +1,140 @@
+use std::{path::PathBuf, time::Duration};
+use tokio::signal::unix::{signal, SignalKind};
+
+use lia_core::{
+    LiaCore,
+    models::{
+        command::Command,
+        run::{ExecEvent, RunHandle, RunOptions}
+    },
+    watcher::FileWatcher
+};
+
+use super::{run::{confirm_runs, describe_retry, print_attempts}, secret::unlock_secrets_for};
+use crate::CLIWatchCommand;
+
+pub async fn watch(lia_core: &LiaCore, watch_cmd: CLIWatchCommand) {
+    let CLIWatchCommand { name, paths, globs, ignore, debounce, yes, args } = watch_cmd;
+    let cmd = match lia_core.get_command_by_name(&name).await {
+        Ok(cmd) => cmd,
+        Err(_) => {
+            eprintln!("Command not found.");
+            std::process::exit(1);
+        }
+    };
+    let paths = if paths.is_empty() { vec![PathBuf::from(".")] } else { paths };
+    let watcher = match FileWatcher::new(&paths, &globs, &ignore, debounce) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("Error watching files: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let options = RunOptions { args, ..RunOptions::default() };
+    let run = [(cmd.name.clone(), cmd, options)];
+    if !yes {
+        confirm_runs(lia_core, &run);
+    }
+    unlock_secrets_for(lia_core, &run).await;
+    let [(_, cmd, options)] = run;
+    watch_command(lia_core, cmd, options, watcher).await;
+    std::process::exit(130);
+}
+
+/// Runs `cmd`, then reruns it on every change reported by `watcher`, stopping the previous run first. Returns on Ctrl-C.
+async fn watch_command(lia_core: &LiaCore, cmd: Command, options: RunOptions, mut watcher: FileWatcher) {
+    let mut interrupt = match signal(SignalKind::interrupt()) {
+        Ok(interrupt) => interrupt,
+        Err(e) => {
+            eprintln!("Error watching files: {}", e);
+            return;
+        }
+    };
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut run_number = 1;
+    println!("[lia] Watching for changes; running {} (run {})", cmd.name, run_number);
+    let mut run = start_watched_run(lia_core, &cmd, &options, &tx);
+
+    loop {
+        tokio::select! {
+            Some(event) = rx.recv() => print_watch_event(&cmd.name, event),
+            changed = watcher.changed() => {
+                let Some(changed) = changed else { break };
+                if let Some(run) = run.take() {
+                    run.cancel();
+                    let _ = run.wait().await;
+                }
+                // Flush the previous run's output so it stays above the separator.
+                while let Ok(event) = rx.try_recv() {
+                    print_watch_event(&cmd.name, event);
+                }
+
+                run_number += 1;
+                let width = crossterm::terminal::size().map(|(columns, _)| columns as usize).unwrap_or(80);
+                println!("{}", "─".repeat(width));
+                println!("[lia] {}; restarting {} (run {})", describe_changes(&changed), cmd.name, run_number);
+                run = start_watched_run(lia_core, &cmd, &options, &tx);
+            }
+            _ = interrupt.recv() => {
+                if let Some(run) = run.take() {
+                    run.cancel();
+                    let _ = run.wait().await;
+                }
+                while let Ok(event) = rx.try_recv() {
+                    print_watch_event(&cmd.name, event);
+                }
+                break;
+            }
+        }
+    }
+}
+
+fn start_watched_run(
+    lia_core: &LiaCore,
+    cmd: &Command,
+    options: &RunOptions,
+    tx: &tokio::sync::mpsc::UnboundedSender<ExecEvent>,
+) -> Option<RunHandle> {
+    match lia_core.start_command_stream(cmd.clone(), options.clone(), tx.clone()) {
+        Ok(run) => Some(run),
+        Err(e) => {
+            eprintln!("[lia] Error running command: {}", e);
+            None
+        }
+    }
+}
+
+fn print_watch_event(name: &str, event: ExecEvent) {
+    match event {
+        ExecEvent::Stdout(line) => println!("{}", line),
+        ExecEvent::Stderr(line) => eprintln!("{}", line),
+        ExecEvent::Exited { code, signal, duration } => {
+            let outcome = match (code, signal) {
+                (Some(code), _) => format!("exited with status {}", code),
+                (None, Some(signal)) => format!("was killed by signal {}", signal),
+                (None, None) => String::from("exited"),
+            };
+            let duration = Duration::from_millis(duration.as_millis() as u64);
+            println!(
+                "[lia] {} {} after {}",
+                name,
+                outcome,
+                humantime::format_duration(duration)
+            );
+        }
+        ExecEvent::Retrying { attempt, max_attempts, delay } => {
+            println!("[lia] {}", describe_retry(&attempt, max_attempts, delay));
+        }
+        ExecEvent::Attempts(attempts) => print_attempts("[lia] ", &attempts),
+    }
+}
+
+fn describe_changes(changed: &[PathBuf]) -> String {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let first = changed[0].strip_prefix(&cwd).unwrap_or(&changed[0]).display();
+    match changed.len() {
+        1 => format!("{} changed", first),
+        n => format!("{} and {} more changed", first, n - 1),
+    }
+}
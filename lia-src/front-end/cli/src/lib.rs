@@ -1 +0,0 @@
-mod commands;
\ No newline at end of file
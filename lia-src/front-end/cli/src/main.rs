@@ -1,20 +1,25 @@
-use std::io::{stdout, Write};
-use tokio::task;
+mod commands;
 
-use clap::{Parser, Subcommand, Args, arg};
-use crossterm::{
-    cursor::{Hide, MoveTo, Show},
-    event::{self, Event, KeyCode, KeyEvent},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType},
+use std::{
+    path::PathBuf,
+    time::Duration
 };
+use regex::Regex;
+
+use clap::{Parser, Subcommand, Args, arg};
 
 use lia_core::{
     LiaCore,
-    errors::LiaCoreError,
-    models::command::{NewCommand, UpdateCommand, Command}
+    models::{
+        schedule::CatchUp,
+        limits::{parse_size, IoPriority, ResourceLimits},
+        retry::{Backoff, RetryPolicy},
+        run::parse_env_var
+    }
 };
-use system::{Logger, set_process_name, SysConfigs};
+use system::{Logger, set_process_name};
+
+use commands::{add, alias, delete, get, jobs, log, run, schedule, secret, update, watch};
 
 #[derive(Parser)]
 #[command(
@@ -58,10 +63,9 @@ enum Commands {
         limit: i64,
     },    
     /// Executes a stored command by its name.
-    Run {
-        /// Name of the command to execute.
-        name: String,
-    },
+    Run(CLIRunCommand),
+    /// Reruns a stored command whenever matching files change.
+    Watch(CLIWatchCommand),
     /// Deletes commands by name or tags.
     Delete {
         /// Name of the command to delete.
@@ -76,6 +80,31 @@ enum Commands {
         #[arg(long)]
         all: bool,
     },
+    /// Manages extra short names for a stored command.
+    Alias {
+        #[command(subcommand)]
+        action: AliasAction,
+    },
+    /// Lists background jobs started with `run --detach`, or manages one.
+    Jobs {
+        #[command(subcommand)]
+        action: Option<JobsAction>,
+        /// The maximum number of jobs to list, newest first.
+        #[arg(short, long, default_value = "20")]
+        limit: i64,
+    },
+    /// Runs stored commands on a cron expression or an interval (requires `lia daemon`).
+    Schedule {
+        #[command(subcommand)]
+        action: ScheduleAction,
+    },
+    /// Manages encrypted secrets, which commands reference as {{secret:name}}.
+    Secret {
+        #[command(subcommand)]
+        action: SecretAction,
+    },
+    /// Runs scheduled commands until stopped. Only one daemon can run at a time.
+    Daemon,
     /// Toggle logging on/off. Must be run with sudo.
     Log {
         /// Enable logging
@@ -87,6 +116,104 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum JobsAction {
+    /// Prints a job's output | Example: lia jobs logs 3 --follow
+    Logs {
+        id: i32,
+        /// Keep printing new output until the job finishes.
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Stops a running job | Example: lia jobs kill 3
+    Kill {
+        id: i32,
+    },
+    /// Waits for a job to finish and exits with its exit status | Example: lia jobs wait 3
+    Wait {
+        id: i32,
+    },
+    /// Shows how each attempt of a job with retries ended | Example: lia jobs attempts 3
+    Attempts {
+        id: i32,
+    },
+    /// Runs a job; started in the background by `run --detach`.
+    #[command(hide = true)]
+    Supervise {
+        id: i32,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScheduleAction {
+    /// Schedules a command | Example: lia schedule add backup-db "0 3 * * *"
+    Add {
+        /// Name (or alias) of the command.
+        name: String,
+        /// Cron expression: minute hour day-of-month month day-of-week, in local time.
+        #[arg(required_unless_present = "every")]
+        cron: Option<String>,
+        /// Run at a fixed interval instead (e.g. 15m, 2h).
+        #[arg(long, value_parser = humantime::parse_duration, conflicts_with = "cron")]
+        every: Option<Duration>,
+        /// What to do with runs missed while the daemon was down or the previous run was still going: skip, once or all.
+        #[arg(long, default_value = "skip", value_parser = parse_catch_up)]
+        catch_up: CatchUp,
+        /// Schedule commands stored with --confirm or flagged as dangerous without asking.
+        #[arg(long, short = 'y')]
+        yes: bool,
+    },
+    /// Lists schedules.
+    List,
+    /// Removes a schedule | Example: lia schedule rm 2
+    Rm {
+        id: i32,
+    },
+    /// Shows the runs of a schedule, newest first | Example: lia schedule history 2
+    History {
+        id: i32,
+        /// The maximum number of runs to show.
+        #[arg(short, long, default_value = "20")]
+        limit: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum SecretAction {
+    /// Stores a secret, read from the terminal or stdin | Example: lia secret set github_token
+    Set {
+        name: String,
+    },
+    /// Prints a secret's value | Example: lia secret get github_token
+    Get {
+        name: String,
+    },
+    /// Removes a secret | Example: lia secret rm github_token
+    Rm {
+        name: String,
+    },
+    /// Lists the stored secrets' names.
+    List,
+}
+
+#[derive(Subcommand)]
+enum AliasAction {
+    /// Adds aliases to a command | Example: lia alias add git-checkout gco checkout
+    Add {
+        /// Name (or existing alias) of the command.
+        name: String,
+        /// One or more aliases to attach.
+        #[arg(required = true)]
+        aliases: Vec<String>,
+    },
+    /// Removes aliases | Example: lia alias rm gco
+    Rm {
+        /// One or more aliases to remove.
+        #[arg(required = true)]
+        aliases: Vec<String>,
+    },
+}
+
 #[derive(Args)]
 struct CLIAddCommand {
     /// A unique name for the command.
@@ -99,6 +226,37 @@ struct CLIAddCommand {
     /// Comma-separated tags for categorization.
     #[arg(short, long)]
     tags: Option<String>,
+    /// Interpreter to run the command with: sh, bash, zsh, fish, python3, node or a path. Defaults to configs.json.
+    #[arg(short, long)]
+    interpreter: Option<String>,
+    /// Store the command as a token list and execute it directly, without a shell.
+    #[arg(long, conflicts_with = "interpreter")]
+    argv: bool,
+    /// Default working directory (supports ~ and $VARS).
+    #[arg(long)]
+    cwd: Option<String>,
+    /// Environment variable to set when running (repeatable).
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_arg)]
+    env: Vec<(String, String)>,
+    /// Default time limit (e.g. 30s, 5m) after which the command is stopped.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    timeout: Option<Duration>,
+    /// Always run on a pseudo-terminal (for interactive programs).
+    #[arg(long)]
+    tty: bool,
+    /// Comma-separated non-zero exit codes that still count as success (e.g. 1 for grep).
+    #[arg(long, value_delimiter = ',', value_parser = clap::value_parser!(i32).range(1..=255))]
+    ok_exit_codes: Vec<i32>,
+    /// Ask for confirmation before every run.
+    #[arg(long)]
+    confirm: bool,
+    /// Run as root, through sudo when LiA isn't root.
+    #[arg(long)]
+    requires_root: bool,
+    #[command(flatten)]
+    limits: LimitArgs,
+    #[command(flatten)]
+    retry: RetryArgs,
 }
 
 #[derive(Args)]
@@ -114,6 +272,216 @@ struct CLIUpdateCommand {
     /// New tags.
     #[arg(short, long)]
     tags: Option<String>,
+    /// New interpreter. Pass "" to fall back to the configured default.
+    #[arg(short, long)]
+    interpreter: Option<String>,
+    /// Convert the command to a token list executed directly, without a shell.
+    #[arg(long)]
+    to_argv: bool,
+    /// Convert a direct exec command back to running through its interpreter.
+    #[arg(long, conflicts_with = "to_argv")]
+    to_shell: bool,
+    /// New default working directory. Pass "" to clear it.
+    #[arg(long)]
+    cwd: Option<String>,
+    /// Replaces the stored environment variables (repeatable).
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_arg)]
+    env: Vec<(String, String)>,
+    /// New default time limit. Pass 0s to remove it.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    timeout: Option<Duration>,
+    /// Whether to always run on a pseudo-terminal.
+    #[arg(long)]
+    tty: Option<bool>,
+    /// Replaces the accepted non-zero exit codes. Pass without a value to clear them.
+    #[arg(long, value_delimiter = ',', num_args = 0.., value_parser = clap::value_parser!(i32).range(1..=255))]
+    ok_exit_codes: Option<Vec<i32>>,
+    /// Whether to ask for confirmation before every run.
+    #[arg(long)]
+    confirm: Option<bool>,
+    /// Whether the command must run as root.
+    #[arg(long)]
+    requires_root: Option<bool>,
+    /// New resource limits. Pass 0 (or "" for --ionice) to remove one.
+    #[command(flatten)]
+    limits: LimitArgs,
+    /// New retry settings. Pass 0 (or "" for --backoff and --retry-on-output, no codes for --retry-on-exit) to
+    /// remove one.
+    #[command(flatten)]
+    retry: RetryArgs,
+}
+
+#[derive(Args)]
+struct CLIRunCommand {
+    /// Name of the command to execute. Give several to run them all, see --parallel.
+    #[arg(required_unless_present = "tag")]
+    names: Vec<String>,
+    /// Run every command with one of these tags (comma-separated).
+    #[arg(long, conflicts_with = "names")]
+    tag: Option<String>,
+    /// Run several commands concurrently, at most N at a time (defaults to the number of CPUs).
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "0")]
+    parallel: Option<usize>,
+    /// Run the command in every directory matching this pattern, e.g. '~/code/*' (repeatable).
+    #[arg(long = "in-dirs", value_name = "PATTERN", conflicts_with_all = ["cwd", "tag"])]
+    in_dirs: Vec<String>,
+    /// With --in-dirs, only use directories that contain this file or directory, e.g. .git.
+    #[arg(long = "where", value_name = "NAME", requires = "in_dirs")]
+    where_: Option<String>,
+    /// Show what would run (command text, interpreter, directory, environment) without running it.
+    #[arg(long, conflicts_with_all = ["detach", "every"])]
+    dry_run: bool,
+    /// Don't ask for confirmation before commands stored with --confirm or flagged as dangerous.
+    #[arg(long, short = 'y')]
+    yes: bool,
+    /// Working directory for this run, overriding the stored one.
+    #[arg(long)]
+    cwd: Option<PathBuf>,
+    /// Extra environment variable for this run (repeatable).
+    #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_arg)]
+    env: Vec<(String, String)>,
+    /// Time limit for this run (e.g. 30s, 5m), overriding the stored one.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    timeout: Option<Duration>,
+    /// Run on a pseudo-terminal, for interactive programs like htop, vim or ssh.
+    #[arg(long)]
+    tty: bool,
+    /// Run in the background as a job; see `lia jobs`.
+    #[arg(long, conflicts_with = "tty")]
+    detach: bool,
+    /// Rerun at this interval like watch(1), redrawing the output and highlighting changed lines.
+    #[arg(long, value_parser = humantime::parse_duration, conflicts_with_all = ["tty", "detach"])]
+    every: Option<Duration>,
+    /// With --every: stop after this many runs.
+    #[arg(long, requires = "every")]
+    count: Option<u64>,
+    /// With --every: stop once the command exits with this status.
+    #[arg(long, requires = "every")]
+    until_exit: Option<i32>,
+    /// With --every: stop once a line of output matches this regex.
+    #[arg(long, requires = "every", value_parser = parse_regex)]
+    until_match: Option<Regex>,
+    #[command(flatten)]
+    limits: LimitArgs,
+    #[command(flatten)]
+    retry: RetryArgs,
+    /// Run isolated, to try out a command before trusting it: no network, and a read-only filesystem.
+    #[arg(long, conflicts_with = "tty")]
+    sandbox: bool,
+    /// With --sandbox, a directory the command may write to (repeatable).
+    #[arg(long, value_name = "DIR", requires = "sandbox")]
+    writable: Vec<PathBuf>,
+    /// Extra arguments passed to the command, after `--`.
+    #[arg(last = true)]
+    args: Vec<String>,
+}
+
+#[derive(Args)]
+struct CLIWatchCommand {
+    /// Name of the command to run.
+    name: String,
+    /// Directory to watch recursively (repeatable). Defaults to the current directory.
+    #[arg(long = "path")]
+    paths: Vec<PathBuf>,
+    /// Only react to files matching this glob (repeatable), e.g. '*.rs'.
+    #[arg(long = "glob")]
+    globs: Vec<String>,
+    /// Ignore files matching this glob (repeatable), e.g. 'dist/**'.
+    #[arg(long = "ignore", value_name = "GLOB")]
+    ignore: Vec<String>,
+    /// How long changes must settle before the command is rerun.
+    #[arg(long, default_value = "200ms", value_parser = humantime::parse_duration)]
+    debounce: Duration,
+    /// Don't ask for confirmation if the command is stored with --confirm or flagged as dangerous.
+    #[arg(long, short = 'y')]
+    yes: bool,
+    /// Extra arguments passed to the command, after `--`.
+    #[arg(last = true)]
+    args: Vec<String>,
+}
+
+/// Resource limits applied to the command's process before it starts.
+#[derive(Args)]
+struct LimitArgs {
+    /// CPU time limit (e.g. 30s, 5m); the command is killed once it has used that much.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    cpu_time: Option<Duration>,
+    /// Address space limit (e.g. 512M, 2G); allocations beyond it fail.
+    #[arg(long, value_parser = parse_size_arg)]
+    memory: Option<u64>,
+    /// Maximum number of open files.
+    #[arg(long, value_name = "N")]
+    open_files: Option<u64>,
+    /// Maximum number of processes of the user the command runs as, counting ones started elsewhere.
+    #[arg(long, value_name = "N")]
+    max_procs: Option<u64>,
+    /// Scheduling niceness, from -20 (favoured; needs root) to 19.
+    #[arg(long, allow_negative_numbers = true, value_parser = clap::value_parser!(i32).range(-20..=19))]
+    nice: Option<i32>,
+    /// I/O priority: idle, best-effort[:0-7] or realtime[:0-7] (needs root).
+    #[arg(long, value_name = "CLASS[:LEVEL]", value_parser = parse_io_priority_arg)]
+    ionice: Option<String>,
+}
+
+impl LimitArgs {
+    fn cpu_secs(&self) -> Option<u64> {
+        self.cpu_time.map(|cpu_time| cpu_time.as_secs_f64().ceil() as u64)
+    }
+
+    /// The limits for a single run; zero leaves the command's stored limit in place.
+    fn to_limits(&self) -> ResourceLimits {
+        let positive = |value: Option<u64>| value.filter(|value| *value > 0);
+        ResourceLimits {
+            cpu_secs: positive(self.cpu_secs()),
+            memory_bytes: positive(self.memory),
+            open_files: positive(self.open_files),
+            processes: positive(self.max_procs),
+            nice: self.nice,
+            io_priority: self.ionice.as_deref().and_then(|ionice| IoPriority::parse(ionice).ok()),
+        }
+    }
+}
+
+/// When a failed command is run again, and how long LiA waits before each retry.
+#[derive(Args)]
+struct RetryArgs {
+    /// Run a failed command again, up to N times.
+    #[arg(long, value_name = "N")]
+    retries: Option<u32>,
+    /// Wait before the first retry (e.g. 500ms, 5s). Defaults to 1s.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    retry_delay: Option<Duration>,
+    /// How the wait changes between retries: fixed, or exponential to double it every time.
+    #[arg(long, value_name = "fixed|exponential", value_parser = parse_backoff_arg)]
+    backoff: Option<String>,
+    /// Longest wait between retries with exponential backoff. Defaults to 5m.
+    #[arg(long, value_parser = humantime::parse_duration)]
+    max_retry_delay: Option<Duration>,
+    /// Only retry on these exit statuses (comma-separated; 124 for timeouts, 128+N for signal N).
+    #[arg(long, value_name = "CODES", value_delimiter = ',', num_args = 0.., value_parser = clap::value_parser!(i32).range(1..=255))]
+    retry_on_exit: Option<Vec<i32>>,
+    /// Only retry when a line of output matches this regex.
+    #[arg(long, value_name = "REGEX", value_parser = parse_pattern_arg)]
+    retry_on_output: Option<String>,
+}
+
+impl RetryArgs {
+    /// Total attempts to store; 0 is kept so that `update --retries 0` can remove the policy.
+    fn attempts(&self) -> Option<i32> {
+        self.retries.map(|retries| if retries == 0 { 0 } else { retries.saturating_add(1).min(i32::MAX as u32) as i32 })
+    }
+
+    /// The retry settings for a single run; ones left out keep the command's stored settings.
+    fn to_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            attempts: self.retries.map(|retries| retries.saturating_add(1)),
+            delay: self.retry_delay,
+            backoff: self.backoff.as_deref().and_then(|backoff| Backoff::parse(backoff).ok()),
+            max_delay: self.max_retry_delay.filter(|delay| !delay.is_zero()),
+            on_exit_codes: self.retry_on_exit.clone().unwrap_or_default(),
+            on_output: self.retry_on_output.clone().filter(|pattern| !pattern.is_empty()),
+        }
+    }
 }
 
 #[tokio::main]
@@ -137,310 +505,55 @@ async fn main() {
                 Err(e) => println!("Error initializing database: {}", e),
             }
         }
-        Commands::Add(add_cmd) => {
-            let tags_vec = add_cmd.tags.map(|t| t.split(',').map(|s| s.trim().to_string()).collect());
-            let new_cmd = NewCommand {
-                name: add_cmd.name,
-                description: add_cmd.description,
-                command_text: add_cmd.command_text,
-                tags: tags_vec,
-            };
-            match lia_core.add_command(new_cmd).await {
-                Ok(_) => println!("Command added successfully."),
-                Err(e) => println!("Error adding command: {}", e),
-            }
-        }
-        Commands::Update(update_cmd) => {
-            let tags_vec = update_cmd.tags.map(|t| t.split(',').map(|s| s.trim().to_string()).collect());
-            let new_cmd = UpdateCommand {
-                name: update_cmd.name,
-                new_command_text: update_cmd.command_text,
-                new_description: update_cmd.description,
-                new_tags: tags_vec,
-            };
-            match lia_core.update_command(new_cmd).await {
-                Ok(_) => println!("Command updated successfully."),
-                Err(e) => println!("Error updating command: {}", e),
-            }
-        }
-        Commands::List { limit } => {
-            match lia_core.get_all_commands(limit, 0).await {
-                Ok(commands) => {
-                    display_commands_paginated(commands);
-                }
-                Err(e) => println!("Error retrieving commands: {}", e),
-            }
-        }
-        Commands::Search { query, tags, limit } => {
-            let tags_vec = tags.map(|t| {
-                t.split(',')
-                    .map(|s| s.trim().to_string())
-                    .collect::<Vec<String>>()
-            });
-
-            let query = query.unwrap_or_default();
-
-            let commands = match lia_core.search_commands(&query, tags_vec, limit, 0).await {
-                Ok(c) => c,
-                Err(_) => {
-                    println!("Error searching for commands.");
-                    return;
-                }
-            };
-            display_commands_paginated(commands);
-        }
-        Commands::Run { name } => {
-            let path = match std::env::current_dir() {
-                Ok(p) => p,
-                Err(_) => {
-                    println!("Error getting current directory.");
-                    return;
-                }
-            };
-
-            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
-            // let handle = thread::spawn(move || {
-            //     while let Ok(line) = rx.recv() {
-            //         println!("{}", line);
-            //     }
-            // });
-
-            let handle = task::spawn(async move {
-              while let Some(line) = rx.recv().await {
-                  println!("{}", line);
-              }
-          });
-
-            let cmd = match lia_core.get_command_by_name(&name).await {
-                Ok(cmd) => cmd,
-                Err(_) => {
-                    println!("Command not found.");
-                    return;
-                }
-            };
-
-            match lia_core.run_command_stream(cmd, &path, tx).await {
-                Ok(_) => handle.await.expect("Failed to join thread"),
-                Err(_) => println!("Error running command."),
-            };
-        }
-        Commands::Delete { name, tags , all} => {
-            if name.is_none() && tags.is_none() && !all {
-                eprintln!("Error: You must provide either a name or tags to delete commands.");
-                return;
-            } else if all {
-                println!("Are you sure you want to delete all commands? [y/N]");
-                let mut input = String::new();
-                match std::io::stdin().read_line(&mut input).map_err(LiaCoreError::IoError) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        println!("Error reading input: {}", e);
-                        return;
-                    }
-                }
-                let input = input.trim().to_lowercase();
-
-                if input == "y" || input == "yes" {
-                    match lia_core.delete_all_commands().await {
-                        Ok(_) => (),
-                        Err(_) => {
-                            println!("Error deleting commands.");
-                            return;
-                        }
-                    }
-                    println!("All commands deleted successfully.");
-                } else {
-                    println!("Deletion cancelled.");
-                }
-                return;
-            }
-
-            let tags_vec = tags.map(|t| {
-                t.split(',')
-                    .map(|s| s.trim().to_string())
-                    .collect::<Vec<String>>()
-            });
-
-            let commands_to_delete = match lia_core.find_commands_for_deletion(name.clone(), tags_vec.clone()).await {
-                Ok(c) => c,
-                Err(_) => {
-                    println!("Error finding commands for deletion.");
-                    return;
-                }
-            }; 
-
-            if commands_to_delete.is_empty() {
-                println!("No commands found matching the criteria for deletion.");
-                return;
-            }
-
-            println!("The following commands will be deleted:");
-            for cmd in &commands_to_delete {
-                println!("Name: {}", cmd.name);
-                println!("Description: {}", cmd.description.clone().unwrap_or_default());
-                println!("Command: {}", cmd.command_text);
-                println!("Tags: {:?}", cmd.tags.clone().unwrap_or_default());
-                println!("---");
-            }
-
-            println!("Are you sure you want to delete these commands? [y/N]");
-            let mut input = String::new();
-            match std::io::stdin().read_line(&mut input).map_err(LiaCoreError::IoError) {
-                Ok(_) => (),
-                Err(e) => {
-                    println!("Error reading input: {}", e);
-                    return;
-                }
-            }
-            let input = input.trim().to_lowercase();
-
-            if input == "y" || input == "yes" {
-                match lia_core.delete_commands(name, tags_vec).await {
-                    Ok(_) => (),
-                    Err(_) => {
-                        println!("Error deleting commands.");
-                        return;
-                    }
-                }
-                println!("Commands deleted successfully.");
-            } else {
-                println!("Deletion cancelled.");
-            }
-        }
-        Commands::Log { on, off } => {
-            let is_root = LiaCore::is_sudo_user();
-            if !is_root {
-                println!("This command must be run with sudo.");
-                return;
-            }
-
-            let toggle = on || !off;
-            SysConfigs::set_log(toggle, false, None);
-            println!("Logging turned {}", if toggle { "on" } else { "off" });
-        }
+        Commands::Add(add_cmd) => add::add(&lia_core, add_cmd).await,
+        Commands::Update(update_cmd) => update::update(&lia_core, update_cmd).await,
+        Commands::List { limit } => get::list(&lia_core, limit).await,
+        Commands::Search { query, tags, limit } => get::search(&lia_core, query, tags, limit).await,
+        Commands::Run(run_cmd) => run::run(&lia_core, run_cmd).await,
+        Commands::Watch(watch_cmd) => watch::watch(&lia_core, watch_cmd).await,
+        Commands::Delete { name, tags, all } => delete::delete(&lia_core, name, tags, all).await,
+        Commands::Alias { action } => alias::alias(&lia_core, action).await,
+        Commands::Jobs { action, limit } => jobs::jobs(&lia_core, action, limit).await,
+        Commands::Schedule { action } => schedule::schedule(&lia_core, action).await,
+        Commands::Secret { action } => secret::secret(&lia_core, action).await,
+        Commands::Daemon => schedule::daemon(&lia_core).await,
+        Commands::Log { on, off } => log::log(on, off),
     }
 }
 
-fn display_commands_paginated(commands: Vec<Command>) {
-    const PAGE_SIZE: usize = 10;
-    let mut current_page: usize = 0;
-    let mut display = true;
-    let total_pages = if commands.is_empty() {
-        1
-    } else {
-        (commands.len() + PAGE_SIZE - 1) / PAGE_SIZE
-    };
-
-    if let Err(e) = enable_raw_mode() {
-        eprintln!("Error enabling raw mode: {}", e);
-        return;
-    }
-
-    let mut stdout = stdout();
-    if let Err(e) = execute!(stdout, Hide) {
-        eprintln!("Error hiding cursor: {}", e);
-    }
+fn parse_regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| e.to_string())
+}
 
-    loop {
-        if display{
-            let start = current_page * PAGE_SIZE;
-            let end = std::cmp::min(start + PAGE_SIZE, commands.len());
-            let current_commands = &commands[start..end];
-
-            for (i, cmd) in current_commands.iter().enumerate() {
-                let command_number = start + i + 1;
-
-                if let Err(e) = write!(stdout, "Command {}\r\n", command_number) {
-                    eprintln!("Error writing to stdout: {}", e);
-                    break;
-                }
-                if let Err(e) = write!(stdout, "Name: {}\r\n", cmd.name) {
-                    eprintln!("Error writing to stdout: {}", e);
-                    break;
-                }
-                if let Err(e) = write!(
-                    stdout,
-                    "Description: {}\r\n",
-                    cmd.description.clone().unwrap_or_default()
-                ) {
-                    eprintln!("Error writing to stdout: {}", e);
-                    break;
-                }
-                if let Err(e) = write!(stdout, "Command: {}\r\n", cmd.command_text) {
-                    eprintln!("Error writing to stdout: {}", e);
-                    break;
-                }
-                let tags: Vec<String> = cmd
-                    .tags
-                    .clone()
-                    .unwrap_or_default()
-                    .iter()
-                    .map(|tag| tag.clone())
-                    .collect();
-                if let Err(e) = write!(stdout, "Tags: {:?}\r\n", tags) {
-                    eprintln!("Error writing to stdout: {}", e);
-                    break;
-                }
-                if let Err(e) = write!(stdout, "---\r\n") {
-                    eprintln!("Error writing to stdout: {}", e);
-                    break;
-                }
-            }
+fn parse_catch_up(name: &str) -> Result<CatchUp, String> {
+    CatchUp::parse(name).map_err(|e| e.to_string())
+}
 
-            if let Err(e) = write!(stdout, "Page {}/{}\r\n", current_page + 1, total_pages) {
-                eprintln!("Error writing to stdout: {}", e);
-                break;
-            }
-            if let Err(e) = write!(
-                stdout,
-                "Use Up/Down arrows to navigate, 'q' to quit.\r\n"
-            ) {
-                eprintln!("Error writing to stdout: {}", e);
-                break;
-            }
-            if let Err(e) = write!(stdout, "---\r\n") {
-                eprintln!("Error writing to stdout: {}", e);
-                break;
-            }
+fn parse_env_arg(pair: &str) -> Result<(String, String), String> {
+    parse_env_var(pair).map_err(|e| e.to_string())
+}
 
-            if let Err(e) = stdout.flush() {
-                eprintln!("Error flushing stdout: {}", e);
-                break;
-            }
-        }
+fn parse_size_arg(size: &str) -> Result<u64, String> {
+    parse_size(size).map_err(|e| e.to_string())
+}
 
-        match event::read() {
-            Ok(Event::Key(KeyEvent { code, .. })) => match code {
-                KeyCode::Char('q') | KeyCode::Esc => {
-                    break;
-                }
-                KeyCode::Down | KeyCode::Right | KeyCode::Enter => {
-                    if current_page + 1 < total_pages {
-                        display = true;
-                        current_page += 1;
-                    } else {
-                        display = false;
-                    }
-                }
-                _ => {
-                    display = false;
-                }
-            },
-            Ok(_) => {}
-            Err(e) => {
-                eprintln!("Error reading event: {}", e);
-                break;
-            }
-        }
+/// Normalizes an I/O priority; "" is kept so that `update --ionice ""` can clear it.
+fn parse_io_priority_arg(ionice: &str) -> Result<String, String> {
+    if ionice.is_empty() {
+        return Ok(String::new());
     }
+    IoPriority::parse(ionice).map(|priority| priority.to_string()).map_err(|e| e.to_string())
+}
 
-    if let Err(e) = execute!(stdout, Show) {
-        eprintln!("Error showing cursor: {}", e);
-    }
-    if let Err(e) = disable_raw_mode() {
-        eprintln!("Error disabling raw mode: {}", e);
-    }
-    if let Err(e) = execute!(stdout, Clear(ClearType::All), MoveTo(0, 0)) {
-        eprintln!("Error clearing screen: {}", e);
+/// Checks a backoff name; "" is kept so that `update --backoff ""` can clear it.
+fn parse_backoff_arg(backoff: &str) -> Result<String, String> {
+    if backoff.is_empty() {
+        return Ok(String::new());
     }
+    Backoff::parse(backoff).map(|backoff| backoff.as_str().to_string()).map_err(|e| e.to_string())
+}
+
+/// Checks a regex but keeps it as text, for patterns that get stored.
+fn parse_pattern_arg(pattern: &str) -> Result<String, String> {
+    parse_regex(pattern).map(|_| pattern.to_string())
 }
@@ -8,8 +8,12 @@ use crate::path::{Path, SysPath};
 static SINGLETON: Once = Once::new();
 static mut CONFIGS: Option<Mutex<Configs>> = None;
 
+/// The `configs.json` LiA ships with. Sections left out of the user's copy fall back to it.
+const DEFAULT_CONFIGS: &str = include_str!("../../configs.json");
+
 lazy_static::lazy_static! {
     pub static ref PROCESS_NAME: Mutex<String> = Mutex::new(String::from("LiA"));
+    static ref DEFAULTS: Defaults = serde_json::from_str(DEFAULT_CONFIGS).expect("Invalid bundled configs.json");
 }
 
 pub fn set_process_name<T: AsRef<str>>(name: T) {
@@ -37,10 +41,96 @@ pub struct Log {
     pub kinds: Kinds,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Execution {
+    pub default_interpreter: String,
+    /// Seconds a cancelled or timed-out command gets to exit after SIGTERM before it is killed.
+    #[serde(default = "Execution::default_kill_grace_period_secs")]
+    pub kill_grace_period_secs: u64,
+    /// Run commands that require root through `sudo`; when false, LiA refuses to run them unless it is root.
+    #[serde(default = "Execution::default_elevate_with_sudo")]
+    pub elevate_with_sudo: bool,
+    /// When LiA runs as root through sudo, run commands as the user who invoked it unless they require root.
+    #[serde(default = "Execution::default_drop_privileges")]
+    pub drop_privileges: bool,
+}
+
+impl Execution {
+    fn default_kill_grace_period_secs() -> u64 { 5 }
+
+    fn default_elevate_with_sudo() -> bool { true }
+
+    fn default_drop_privileges() -> bool { true }
+}
+
+impl Default for Execution {
+    fn default() -> Self {
+        Execution {
+            default_interpreter: String::from("sh"),
+            kill_grace_period_secs: Execution::default_kill_grace_period_secs(),
+            elevate_with_sudo: Execution::default_elevate_with_sudo(),
+            drop_privileges: Execution::default_drop_privileges(),
+        }
+    }
+}
+
+/// A pattern that marks a command as risky: it needs confirmation before it runs.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RiskRule {
+    pub name: String,
+    /// Regular expression searched for in the command text.
+    pub pattern: String,
+    /// Why matching commands are risky, shown to the user.
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Risk {
+    pub rules: Vec<RiskRule>,
+}
+
+impl Default for Risk {
+    fn default() -> Self {
+        DEFAULTS.risk.clone()
+    }
+}
+
+/// A pattern for sensitive values that are replaced with `[REDACTED]` in log messages.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RedactionRule {
+    pub name: String,
+    /// Regular expression; when it has a capture group named `secret`, only that part is replaced.
+    pub pattern: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Redaction {
+    pub rules: Vec<RedactionRule>,
+}
+
+impl Default for Redaction {
+    fn default() -> Self {
+        DEFAULTS.redaction.clone()
+    }
+}
+
+/// The sections of `DEFAULT_CONFIGS` used as defaults.
+#[derive(Deserialize)]
+struct Defaults {
+    risk: Risk,
+    redaction: Redaction,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Configs {
     log: Log,
     profile: Option<Profile>,
+    #[serde(default)]
+    execution: Execution,
+    #[serde(default)]
+    risk: Risk,
+    #[serde(default)]
+    redaction: Redaction,
 }
 
 impl Configs {
@@ -126,6 +216,42 @@ impl Configs {
         self.profile.as_ref().unwrap()
     }
 
+    pub fn get_execution() -> Execution {
+        let execution = {
+            let config = Configs::open().lock().unwrap();
+            config.execution().clone()
+        };
+        execution
+    }
+
+    fn execution(&self) -> &Execution {
+        &self.execution
+    }
+
+    pub fn get_risk() -> Risk {
+        let risk = {
+            let config = Configs::open().lock().unwrap();
+            config.risk().clone()
+        };
+        risk
+    }
+
+    fn risk(&self) -> &Risk {
+        &self.risk
+    }
+
+    pub fn get_redaction() -> Redaction {
+        let redaction = {
+            let config = Configs::open().lock().unwrap();
+            config.redaction().clone()
+        };
+        redaction
+    }
+
+    fn redaction(&self) -> &Redaction {
+        &self.redaction
+    }
+
     pub fn reload() {
         let config_path: SysPath = join_root!("configs.json");
         let content: String = std::fs::read_to_string(config_path).unwrap();
@@ -139,7 +265,7 @@ impl Configs {
 
 #[cfg(test)]
 mod tests {
-    use crate::config::Configs;
+    use crate::config::{Configs, Redaction, Risk};
 
     #[test]
     fn test_new() {
@@ -166,4 +292,28 @@ mod tests {
         assert!(Configs::get_log().on);
         assert!(!Configs::get_log().save);
     }
+
+    #[test]
+    fn test_get_risk() {
+        let risk = Configs::get_risk();
+        assert!(risk.rules.iter().any(|rule| rule.name == "rm-root"));
+    }
+
+    #[test]
+    fn test_get_redaction() {
+        let redaction = Configs::get_redaction();
+        assert!(redaction.rules.iter().any(|rule| rule.name == "authorization-header"));
+    }
+
+    #[test]
+    fn test_defaults() {
+        assert!(Risk::default().rules.iter().any(|rule| rule.name == "fork-bomb"));
+        assert!(Redaction::default().rules.iter().any(|rule| rule.name == "private-key"));
+    }
+
+    #[test]
+    fn test_get_execution() {
+        let execution = Configs::get_execution();
+        assert!(!execution.default_interpreter.is_empty());
+    }
 }
@@ -7,7 +7,7 @@ mod logger;
 pub use logger::Logger;
 
 mod config;
-pub use config::{set_process_name, get_process_name, Configs as SysConfigs};
+pub use config::{set_process_name, get_process_name, Configs as SysConfigs, RiskRule, Redaction, RedactionRule};
 
 mod env;
 pub use env::config::Config as EnvConfig;
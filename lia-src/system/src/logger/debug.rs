@@ -5,7 +5,7 @@ use std::{
 };
 use chrono::Local as time;
 
-use super::{ILogger, LoggerEssentials, Configs};
+use super::{redact::redact, ILogger, LoggerEssentials, Configs};
 use crate::{config::get_process_name, path::{join_root, Path, SysPath}};
 
 /// Logger for development purposes. This Logger will save the logs in a .txt file.
@@ -33,7 +33,7 @@ macro_rules! log_level_impl {
                     timestamp,
                     location.file(),
                     location.line(),
-                    message.as_ref()
+                    redact(message.as_ref())
                 );
 
                 if save { logger.save(&message); }
@@ -1,4 +1,5 @@
 mod debug;
+mod redact;
 
 use super::config::{Configs, Profile};
 use debug::DebugLogger;
@@ -33,6 +33,12 @@ impl Path {
         migrations_path
     }
 
+    pub fn get_job_logs_path() -> SysPath {
+        let job_logs_path: SysPath = join_root!("logs", "jobs");
+
+        job_logs_path
+    }
+
     pub fn join_root(file_folder_names: Vec<&str>) -> SysPath {
         let path: MutexGuard<Path> = Path::get().lock().unwrap();
         let mut joined_path: SysPath = path.root.clone();